@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use elgamal_wasm::generic::{Encryption, PublicKey};
+use elgamal_wasm::precompute::PreparedPublicKey;
+use num_bigint::BigInt;
+mod profiler;
+use profiler::FlameGraphProfiler;
+use std::time::Duration;
+
+const SEED: [u32; 1] = [2929];
+
+fn encrypt_benchmark(pub_key: &PublicKey<BigInt>, msg: &String) {
+    let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+    for _ in 0..10 {
+        msg.encrypt(pub_key, &mut rng);
+    }
+}
+
+fn prepared_encrypt_benchmark(pub_key: &PreparedPublicKey, msg: &str) {
+    let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+    for _ in 0..10 {
+        pub_key.encrypt(msg, &mut rng);
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let pub_key = elgamal_wasm::generate_pub_key(&SEED, 256, 16).0;
+    let prepared = PreparedPublicKey::new(pub_key.clone());
+    let msg = String::from("a message long enough to span several encrypted chunks");
+    let mut group = c.benchmark_group("encrypt-benchmark");
+    group
+        .significance_level(0.1)
+        .measurement_time(Duration::from_secs(20));
+    group.bench_function("encrypt x10", |b| {
+        b.iter(|| encrypt_benchmark(&pub_key, &msg))
+    });
+    group.bench_function("prepared encrypt x10", |b| {
+        b.iter(|| prepared_encrypt_benchmark(&prepared, &msg))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().with_profiler(FlameGraphProfiler::new(100));
+    targets = criterion_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,113 @@
+//! batch mod
+//! encrypt many independent messages to the same public key.
+//! All randomness is drawn from the RNG up front in message order, so the output is
+//! identical to calling `encrypt` in a loop with the same RNG, whether or not the
+//! exponentiations run in parallel (`parallel` feature).
+use crate::elgamal::{encode_message, format_cipher_pairs, random_exponent};
+use crate::generic::PublicKey;
+use crate::precompute::PreparedPublicKey;
+use num_bigint::BigInt;
+use rand_core::RngCore;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Encoded chunks of one message with the random exponent drawn for each chunk.
+struct BatchJob {
+    codes: Vec<BigInt>,
+    exponents: Vec<BigInt>,
+}
+
+/// Encrypts every message to a prepared key, returning one ciphertext per message.
+/// Returns None if the key cannot encode messages.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::batch::encrypt_batch;
+/// use elgamal::precompute::PreparedPublicKey;
+/// let (pubkey, mut rng) = elgamal::generate_pub_key(&[2929], 32, 32);
+/// let prepared = PreparedPublicKey::new(pubkey);
+/// let ciphers = encrypt_batch(&prepared, &["first", "second"], &mut rng).unwrap();
+/// assert_eq!(ciphers.len(), 2);
+/// ```
+pub fn encrypt_batch<S, R>(
+    key: &PreparedPublicKey,
+    messages: &[S],
+    rng: &mut R,
+) -> Option<Vec<String>>
+where
+    S: AsRef<str>,
+    R: RngCore,
+{
+    let pubkey = key.public_key();
+    let jobs: Vec<BatchJob> = messages
+        .iter()
+        .map(|msg| {
+            let codes = encode_message(msg.as_ref(), pubkey)?;
+            let exponents = codes
+                .iter()
+                .map(|_| random_exponent(rng, &pubkey.group.p))
+                .collect();
+            Some(BatchJob { codes, exponents })
+        })
+        .collect::<Option<_>>()?;
+    #[cfg(feature = "parallel")]
+    let jobs_iter = jobs.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let jobs_iter = jobs.iter();
+    Some(jobs_iter.map(|job| encrypt_job(key, job)).collect())
+}
+
+/// Prepares the key once and encrypts every message to it.
+pub fn encrypt_batch_with_key<S, R>(
+    key: &PublicKey<BigInt>,
+    messages: &[S],
+    rng: &mut R,
+) -> Option<Vec<String>>
+where
+    S: AsRef<str>,
+    R: RngCore,
+{
+    let prepared = PreparedPublicKey::new(key.clone());
+    encrypt_batch(&prepared, messages, rng)
+}
+
+fn encrypt_job(key: &PreparedPublicKey, job: &BatchJob) -> String {
+    let p = &key.public_key().group.p;
+    let cipher_pairs: Vec<(BigInt, BigInt)> = job
+        .codes
+        .iter()
+        .zip(job.exponents.iter())
+        .map(|(i_code, y)| {
+            let (c, h_y) = key.pow_g_h(y);
+            (c, (i_code * h_y) % p)
+        })
+        .collect();
+    format_cipher_pairs(&cipher_pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::Encryption;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_batch_matches_encrypt_loop() {
+        let (pubkey, _) = crate::generate_pub_key(&SEED, 64, 16);
+        let messages = vec![
+            String::from("first message"),
+            String::from(""),
+            String::from("a somewhat longer third message"),
+        ];
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let expected: Vec<String> = messages
+            .iter()
+            .map(|msg| msg.encrypt(&pubkey, &mut rng).unwrap())
+            .collect();
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let result = encrypt_batch_with_key(&pubkey, &messages, &mut rng);
+        assert_eq!(Some(expected), result);
+    }
+}
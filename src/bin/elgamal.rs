@@ -0,0 +1,350 @@
+//! elgamal command line tool, built with the `cli` feature.
+//! generates keys and walks key chains, encrypts, decrypts, validates keys and
+//! recovers private keys of small groups. keys and ciphertexts are read and
+//! written as hex strings, hex encoded SCALE or JSON.
+use clap::{Parser, Subcommand, ValueEnum};
+use codec::{Decode, Encode};
+use elgamal::generic::{Decryption, Encryption, PrivateKey, PublicKey};
+use elgamal::groups::StandardGroup;
+use elgamal::{Ciphertext, KeyFormat, KeyGenerator, RawKey, RawPublicKey};
+use elgamal_wasm as elgamal;
+use num_bigint::{BigInt, BigUint};
+use rand_core::{OsRng, RngCore};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(
+    name = "elgamal",
+    version,
+    about = "ElGamal key generation and encryption"
+)]
+struct Cli {
+    /// Format of keys and ciphertexts read and written.
+    #[arg(long, value_enum, default_value_t = Format::Hex, global = true)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// "0xp, 0xg, 0xh, bit_length" keys and "c d c d " ciphertexts.
+    Hex,
+    /// Hex encoded SCALE of `RawPublicKey`, public keys of at most 256 bits only:
+    /// subcommands reading or writing private keys or ciphertexts refuse it up front.
+    Scale,
+    /// The JSON shape of the `serde` feature.
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Scheme {
+    /// `generate_pub_key`, the key chain generator.
+    Legacy,
+    /// `generate_pub_key_v2` with domain separated streams.
+    V2,
+    /// `generate_qr_pub_key` in the quadratic residue subgroup.
+    Qr,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a public key from a seed, or a key pair on a standard group.
+    Keygen {
+        /// Decimal or 0x prefixed hex seed.
+        #[arg(long, required_unless_present = "group")]
+        seed: Option<String>,
+        #[arg(long, default_value_t = 256)]
+        bits: u32,
+        #[arg(long, value_enum, default_value_t = Scheme::Legacy)]
+        scheme: Scheme,
+        /// Standard group name such as ffdhe2048, prints the public then the private key.
+        #[arg(long, conflicts_with_all = ["bits", "scheme"])]
+        group: Option<String>,
+    },
+    /// Walk the key chain, printing the key of every step.
+    Yield {
+        /// Decimal or 0x prefixed hex seed of the first key.
+        #[arg(long, required_unless_present = "key", conflicts_with = "key")]
+        seed: Option<String>,
+        /// Public key to start from.
+        #[arg(long)]
+        key: Option<String>,
+        /// Bit length of the chain, defaults to the key's bit length with --key and 256 otherwise.
+        #[arg(long)]
+        bits: Option<u32>,
+        #[arg(long, default_value_t = 1)]
+        steps: u32,
+    },
+    /// Encrypt a message to a public key.
+    Encrypt {
+        #[arg(long)]
+        key: String,
+        #[arg(long)]
+        message: String,
+        /// Seed of the encryption randomness, for reproducible output only.
+        #[arg(long)]
+        seed: Option<String>,
+    },
+    /// Decrypt a ciphertext with a private key.
+    Decrypt {
+        #[arg(long)]
+        key: String,
+        #[arg(long)]
+        ciphertext: String,
+    },
+    /// Check that a public key is on a safe prime group.
+    Validate {
+        #[arg(long)]
+        key: String,
+        #[arg(long, default_value_t = 16)]
+        confidence: u32,
+    },
+    /// Recover the private key of a small public key by discrete log.
+    Crack {
+        #[arg(long)]
+        key: String,
+        /// Refuse keys with a larger p, the work and the table of baby steps grow with
+        /// 2^(bits / 2), about 2^20 entries at the default.
+        #[arg(long, default_value_t = 40)]
+        max_bits: u32,
+    },
+}
+
+type CliResult<T> = Result<T, String>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let format = cli.format;
+    check_format(&cli.command, format)?;
+    match cli.command {
+        Command::Keygen {
+            seed,
+            bits,
+            scheme,
+            group,
+        } => {
+            if let Some(name) = group {
+                let group = StandardGroup::from_name(&name)
+                    .ok_or_else(|| format!("unknown group {}", name))?;
+                let (pubkey, private_key) = match seed {
+                    Some(seed) => {
+                        let mut rng = mt19937::MT19937::new_with_slice_seed(&parse_seed(&seed)?);
+                        group.generate_key_pair(&mut rng)
+                    }
+                    None => group.generate_key_pair(&mut OsRng),
+                };
+                println!("{}", write_public_key(&pubkey, format)?);
+                println!("{}", write_private_key(&private_key, format)?);
+            } else {
+                let seed = parse_seed(&seed.unwrap())?;
+                let confidence = <PublicKey<BigInt> as KeyGenerator>::CONFIDENCE;
+                let (pubkey, _) = match scheme {
+                    Scheme::Legacy => elgamal::generate_pub_key(&seed, bits, confidence),
+                    Scheme::V2 => elgamal::generate_pub_key_v2(&seed, bits, confidence),
+                    Scheme::Qr => elgamal::generate_qr_pub_key(&seed, bits, confidence),
+                };
+                println!("{}", write_public_key(&pubkey, format)?);
+            }
+        }
+        Command::Yield {
+            seed,
+            key,
+            bits,
+            steps,
+        } => {
+            let (mut pubkey, bits) = match key {
+                Some(key) => {
+                    let pubkey = read_public_key(&key, format)?;
+                    let bits = match bits {
+                        Some(bits) if bits != pubkey.group.bit_length => {
+                            return Err(format!(
+                                "--bits {} conflicts with the key's bit length {}",
+                                bits, pubkey.group.bit_length
+                            ));
+                        }
+                        _ => pubkey.group.bit_length,
+                    };
+                    (pubkey, bits)
+                }
+                None => {
+                    let seed = parse_seed(&seed.unwrap())?;
+                    let bits = bits.unwrap_or(256);
+                    let confidence = <PublicKey<BigInt> as KeyGenerator>::CONFIDENCE;
+                    let (pubkey, _) = elgamal::generate_pub_key(&seed, bits, confidence);
+                    println!("{}", write_public_key(&pubkey, format)?);
+                    (pubkey, bits)
+                }
+            };
+            for _ in 0..steps {
+                pubkey = pubkey.yield_pubkey(bits);
+                println!("{}", write_public_key(&pubkey, format)?);
+            }
+        }
+        Command::Encrypt { key, message, seed } => {
+            let pubkey = read_public_key(&key, format)?;
+            let cipher = match seed {
+                Some(seed) => {
+                    let mut rng = mt19937::MT19937::new_with_slice_seed(&parse_seed(&seed)?);
+                    encrypt(&message, &pubkey, &mut rng)?
+                }
+                None => encrypt(&message, &pubkey, &mut OsRng)?,
+            };
+            let cipher = Ciphertext::parse(&cipher).expect("encrypt prints cipher pairs");
+            println!("{}", write_ciphertext(&cipher, format)?);
+        }
+        Command::Decrypt { key, ciphertext } => {
+            let private_key = read_private_key(&key, format)?;
+            let cipher = read_ciphertext(&ciphertext, format)?;
+            let plaintext = cipher
+                .to_cipher_string()
+                .decrypt(&private_key)
+                .ok_or("ciphertext does not decrypt under this key")?;
+            println!("{}", plaintext);
+        }
+        Command::Validate { key, confidence } => {
+            let pubkey = read_public_key(&key, format)?;
+            pubkey
+                .group
+                .validate(confidence)
+                .map_err(|err| format!("invalid group: {:?}", err))?;
+            if pubkey.h <= BigInt::from(0) || pubkey.h >= pubkey.group.p {
+                return Err("h is not in [1, p - 1]".to_string());
+            }
+            println!("valid");
+        }
+        Command::Crack { key, max_bits } => {
+            let pubkey = read_public_key(&key, format)?;
+            let group = &pubkey.group;
+            if group.p.bits() > max_bits as u64 {
+                return Err(format!(
+                    "p has {} bits, more than --max-bits {}",
+                    group.p.bits(),
+                    max_bits
+                ));
+            }
+            let order = &group.p - 1u32;
+            let x = elgamal::utils::discrete_log(&group.g, &pubkey.h, &group.p, &order)
+                .ok_or("h is not a power of g")?;
+            let private_key = group.private_key(x);
+            println!("{}", write_private_key(&private_key, format)?);
+        }
+    }
+    Ok(())
+}
+
+fn encrypt<R: RngCore>(
+    message: &str,
+    pubkey: &PublicKey<BigInt>,
+    rng: &mut R,
+) -> CliResult<String> {
+    message
+        .to_string()
+        .encrypt(pubkey, rng)
+        .ok_or_else(|| "the key's bit length is too large for its group".to_string())
+}
+
+/// Seed words of a decimal or 0x prefixed hex integer, as `BigUint::to_u32_digits`.
+fn parse_seed(seed: &str) -> CliResult<Vec<u32>> {
+    let seed = seed.trim();
+    let parsed = match seed.strip_prefix("0x") {
+        Some(digits) => BigUint::parse_bytes(digits.as_bytes(), 16),
+        None => BigUint::parse_bytes(seed.as_bytes(), 10),
+    };
+    let seed = parsed.ok_or_else(|| format!("invalid seed {}", seed))?;
+    Ok(seed.to_u32_digits())
+}
+
+/// Rejects a format the subcommand cannot read or write before doing any work,
+/// so no subcommand prints half of its output.
+fn check_format(command: &Command, format: Format) -> CliResult<()> {
+    if format != Format::Scale {
+        return Ok(());
+    }
+    let needs = match command {
+        Command::Keygen { group: Some(_), .. } => "keygen --group prints a private key",
+        Command::Encrypt { .. } => "encrypt prints a ciphertext",
+        Command::Decrypt { .. } => "decrypt reads a private key and a ciphertext",
+        Command::Crack { .. } => "crack prints a private key",
+        _ => return Ok(()),
+    };
+    Err(format!(
+        "{}, --format scale only holds public keys, use hex or json",
+        needs
+    ))
+}
+
+fn unsupported(what: &str, format: Format) -> String {
+    let name = format.to_possible_value().unwrap().get_name().to_string();
+    format!("{} cannot be written or read as {}", what, name)
+}
+
+fn read_public_key(input: &str, format: Format) -> CliResult<PublicKey<BigInt>> {
+    match format {
+        Format::Hex => {
+            PublicKey::try_from_hex_str(input).ok_or_else(|| "invalid public key".to_string())
+        }
+        Format::Scale => {
+            let bytes = hex::decode(input.trim().trim_start_matches("0x"))
+                .map_err(|err| err.to_string())?;
+            let raw_key =
+                RawPublicKey::decode(&mut bytes.as_slice()).map_err(|err| err.to_string())?;
+            Ok(PublicKey::from_raw(raw_key))
+        }
+        Format::Json => serde_json::from_str(input).map_err(|err| err.to_string()),
+    }
+}
+
+fn write_public_key(pubkey: &PublicKey<BigInt>, format: Format) -> CliResult<String> {
+    match format {
+        Format::Hex => Ok(pubkey.to_hex_str()),
+        Format::Scale => {
+            let raw_key = RawPublicKey::try_from(pubkey.clone())
+                .map_err(|_| unsupported("public keys over 256 bits", format))?;
+            Ok(format!("0x{}", hex::encode(raw_key.encode())))
+        }
+        Format::Json => serde_json::to_string(pubkey).map_err(|err| err.to_string()),
+    }
+}
+
+fn read_private_key(input: &str, format: Format) -> CliResult<PrivateKey<BigInt>> {
+    match format {
+        Format::Hex => input.parse().map_err(|_| "invalid private key".to_string()),
+        Format::Scale => Err(unsupported("private keys", format)),
+        Format::Json => serde_json::from_str(input).map_err(|err| err.to_string()),
+    }
+}
+
+fn write_private_key(private_key: &PrivateKey<BigInt>, format: Format) -> CliResult<String> {
+    match format {
+        Format::Hex => Ok(private_key.to_hex_str()),
+        Format::Scale => Err(unsupported("private keys", format)),
+        Format::Json => serde_json::to_string(private_key).map_err(|err| err.to_string()),
+    }
+}
+
+fn read_ciphertext(input: &str, format: Format) -> CliResult<Ciphertext> {
+    match format {
+        Format::Hex => input.parse().map_err(|_| "invalid ciphertext".to_string()),
+        Format::Scale => Err(unsupported("ciphertexts", format)),
+        Format::Json => serde_json::from_str(input).map_err(|err| err.to_string()),
+    }
+}
+
+fn write_ciphertext(cipher: &Ciphertext, format: Format) -> CliResult<String> {
+    match format {
+        Format::Hex => Ok(cipher.to_string()),
+        Format::Scale => Err(unsupported("ciphertexts", format)),
+        Format::Json => serde_json::to_string(cipher).map_err(|err| err.to_string()),
+    }
+}
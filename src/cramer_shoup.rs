@@ -0,0 +1,312 @@
+//! cramer_shoup mod
+//! Cramer-Shoup encryption in the quadratic residue subgroup of a safe prime p = 2q + 1,
+//! secure against adaptive chosen ciphertext attacks under DDH.
+//! messages are chunked and encoded like `Encryption for String`, every chunk gets its own
+//! randomness, and the hash alpha covers the key and all chunks, so changing, dropping or
+//! reordering any chunk makes every validity check fail.
+use crate::elgamal::{
+    decode_message, encode_message, format_cipher_pairs, parse_cipher_pairs, unblind,
+};
+use crate::generic::{Decryption, Encryption};
+use crate::groups::GroupParams;
+use crate::proofs::{challenge, in_group, is_subgroup_generator};
+use crate::utils;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use rand_core::RngCore;
+use std::sync::Arc;
+
+/// Domain tag of the hash alpha.
+const DOMAIN: &[u8] = b"elgamal_wasm/cramer-shoup";
+
+/// Errors of Cramer-Shoup key generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CramerShoupError {
+    /// g of the group does not generate the quadratic residue subgroup.
+    InvalidGenerator,
+}
+
+/// Type alias for Cramer-Shoup results.
+pub type CramerShoupResult<T> = std::result::Result<T, CramerShoupError>;
+
+/// Cramer-Shoup public key, all elements are quadratic residues mod p.
+/// g1 is the generator g of the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CramerShoupPublicKey<I> {
+    pub group: Arc<GroupParams<I>>,
+    pub g2: I,
+    pub c: I,
+    pub d: I,
+    pub h: I,
+}
+
+/// Cramer-Shoup private key, all exponents are in [0, q - 1].
+/// g1 is the generator g of the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CramerShoupPrivateKey<I> {
+    pub group: Arc<GroupParams<I>>,
+    pub g2: I,
+    pub x1: I,
+    pub x2: I,
+    pub y1: I,
+    pub y2: I,
+    pub z: I,
+}
+
+impl CramerShoupPrivateKey<BigInt> {
+    /// Computes the public key.
+    pub fn public_key(&self) -> CramerShoupPublicKey<BigInt> {
+        let (p, g1) = (&self.group.p, &self.group.g);
+        let c = (g1.modpow(&self.x1, p) * self.g2.modpow(&self.x2, p)) % p;
+        let d = (g1.modpow(&self.y1, p) * self.g2.modpow(&self.y2, p)) % p;
+        CramerShoupPublicKey {
+            group: self.group.clone(),
+            g2: self.g2.clone(),
+            c,
+            d,
+            h: g1.modpow(&self.z, p),
+        }
+    }
+}
+
+/// Generates a key pair on the group with a random second generator g2 of the
+/// quadratic residue subgroup. g of the group is g1 and must generate the quadratic
+/// residue subgroup too, e.g. a group of `generate_qr_pub_key` or a standard group,
+/// other groups are rejected with `CramerShoupError::InvalidGenerator`.
+/// # Logic Desc
+/// ```text
+/// x1, x2, y1, y2, z are random in [0, q - 1]
+/// c = g1^x1 * g2^x2 mod p
+/// d = g1^y1 * g2^y2 mod p
+/// h = g1^z mod p
+/// ```
+pub fn generate_key_pair<R: RngCore>(
+    group: &Arc<GroupParams>,
+    rng: &mut R,
+) -> CramerShoupResult<(CramerShoupPublicKey<BigInt>, CramerShoupPrivateKey<BigInt>)> {
+    let q = &group.q;
+    if !is_subgroup_generator(&group.g, &group.p, q) {
+        return Err(CramerShoupError::InvalidGenerator);
+    }
+    let mut exponent = || utils::gen_bigint_range(rng, &BigInt::from(0), &(q - 1u32));
+    let (x1, x2, y1, y2, z) = (exponent(), exponent(), exponent(), exponent(), exponent());
+    let private_key = CramerShoupPrivateKey {
+        group: group.clone(),
+        g2: random_qr_generator(&group.p, rng),
+        x1,
+        x2,
+        y1,
+        y2,
+        z,
+    };
+    Ok((private_key.public_key(), private_key))
+}
+
+/// Generates a key pair from a seed, on the safe prime `generate_pub_key` picks for the seed
+/// with a random generator g1 of the quadratic residue subgroup.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::cramer_shoup::generate_key_pair_from_seed;
+/// use elgamal::generic::{Decryption, Encryption};
+/// let (pubkey, private_key, mut rng) = generate_key_pair_from_seed(&[2929], 64, 16);
+/// let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+/// assert_eq!(Some(String::from("message")), cipher.decrypt(&private_key));
+/// ```
+pub fn generate_key_pair_from_seed(
+    seed: &[u32],
+    bit_length: u32,
+    i_confidence: u32,
+) -> (
+    CramerShoupPublicKey<BigInt>,
+    CramerShoupPrivateKey<BigInt>,
+    mt19937::MT19937,
+) {
+    let mut rng = mt19937::MT19937::new_with_slice_seed(seed);
+    let p = utils::random_prime_bigint(bit_length, i_confidence, &mut rng);
+    let g1 = random_qr_generator(&p, &mut rng);
+    let group = Arc::new(GroupParams::new(p, g1, bit_length));
+    let (pubkey, private_key) =
+        generate_key_pair(&group, &mut rng).expect("g1 generates the quadratic residue subgroup");
+    (pubkey, private_key, rng)
+}
+
+/// Picks a^2 mod p for random a, skipping 1, so it generates the order q subgroup.
+fn random_qr_generator<R: RngCore>(p: &BigInt, rng: &mut R) -> BigInt {
+    let big_int_2 = BigInt::from(2);
+    loop {
+        let a = utils::gen_bigint_range(rng, &big_int_2, &(p - 2u32));
+        let g = a.modpow(&big_int_2, p);
+        if g != BigInt::from(1) {
+            return g;
+        }
+    }
+}
+
+/// The hash alpha over the key and the (u1, u2, e) of every chunk, reduced mod q.
+fn alpha(key: &CramerShoupPublicKey<BigInt>, quads: &[[BigInt; 4]]) -> BigInt {
+    let group = &key.group;
+    let count = BigInt::from(quads.len());
+    let mut elements = vec![&group.p, &group.g, &key.g2, &key.c, &key.d, &key.h, &count];
+    for [u1, u2, e, _] in quads {
+        elements.extend([u1, u2, e]);
+    }
+    challenge(DOMAIN, &[], &elements, &group.q)
+}
+
+impl Encryption<BigInt, CramerShoupPublicKey<BigInt>> for String {
+    /// Encrypts a string to a Cramer-Shoup key, as space separated quadruples "u1 u2 e v ".
+    /// # Logic Desc
+    /// ```text
+    /// m[i] are the quadratic residue codes of the message chunks
+    /// r[i] is random in [1, q - 1]
+    /// u1[i] = g1^r[i], u2[i] = g2^r[i], e[i] = h^r[i] * m[i] mod p
+    /// alpha = H(key, n, u1[0], u2[0], e[0], ..., u1[n-1], u2[n-1], e[n-1]) mod q
+    /// v[i] = c^r[i] * d^(r[i] * alpha) mod p
+    /// ```
+    fn encrypt<R: RngCore>(
+        &self,
+        key: &CramerShoupPublicKey<BigInt>,
+        rng: &mut R,
+    ) -> Option<String> {
+        let (p, q) = (&key.group.p, &key.group.q);
+        let elgamal_key = key.group.public_key(key.h.clone());
+        let mut exponents = Vec::new();
+        let mut quads: Vec<[BigInt; 4]> = encode_message(self, &elgamal_key)?
+            .into_iter()
+            .map(|m| {
+                let r = utils::gen_bigint_range(rng, &BigInt::from(1), &(q - 1u32));
+                let u1 = key.group.g.modpow(&r, p);
+                let u2 = key.g2.modpow(&r, p);
+                let e = (key.h.modpow(&r, p) * m) % p;
+                exponents.push(r);
+                [u1, u2, e, BigInt::from(0)]
+            })
+            .collect();
+        let alpha = alpha(key, &quads);
+        for (quad, r) in quads.iter_mut().zip(&exponents) {
+            let r_alpha = (r * &alpha).mod_floor(q);
+            quad[3] = (key.c.modpow(r, p) * key.d.modpow(&r_alpha, p)) % p;
+        }
+        let pairs: Vec<(BigInt, BigInt)> = quads
+            .into_iter()
+            .flat_map(|[u1, u2, e, v]| [(u1, u2), (e, v)])
+            .collect();
+        Some(format_cipher_pairs(&pairs))
+    }
+}
+
+impl Decryption<BigInt, CramerShoupPrivateKey<BigInt>> for String {
+    /// Decrypts quadruples "u1 u2 e v ", read as pairs (u1, u2), (e, v) by `parse_cipher_pairs`.
+    /// Returns None if the pairs do not form quadruples or any chunk fails the validity check.
+    /// # Logic Desc
+    /// ```text
+    /// u1[i] and u2[i] must be quadratic residues mod p
+    /// v[i] = u1[i]^(x1 + y1 * alpha) * u2[i]^(x2 + y2 * alpha) mod p
+    /// m[i] = e[i] * u1[i]^-z mod p
+    /// ```
+    fn decrypt(&self, key: &CramerShoupPrivateKey<BigInt>) -> Option<String> {
+        let (p, q) = (&key.group.p, &key.group.q);
+        let pairs = parse_cipher_pairs(self)?;
+        if pairs.len() % 2 != 0 {
+            return None;
+        }
+        let quads: Vec<[BigInt; 4]> = pairs
+            .chunks(2)
+            .map(|quad| {
+                let [(u1, u2), (e, v)] = [quad[0].clone(), quad[1].clone()];
+                [u1, u2, e, v]
+            })
+            .collect();
+        let alpha = alpha(&key.public_key(), &quads);
+        let exp1 = (&key.x1 + &key.y1 * &alpha).mod_floor(q);
+        let exp2 = (&key.x2 + &key.y2 * &alpha).mod_floor(q);
+        let mut codes = Vec::with_capacity(quads.len());
+        for [u1, u2, e, v] in &quads {
+            for element in [u1, u2, e, v] {
+                if !in_group(element, p) {
+                    return None;
+                }
+            }
+            if !utils::is_quadratic_residue(u1, p) || !utils::is_quadratic_residue(u2, p) {
+                return None;
+            }
+            if (u1.modpow(&exp1, p) * u2.modpow(&exp2, p)) % p != *v {
+                return None;
+            }
+            codes.push(unblind(e, &u1.modpow(&key.z, p), p));
+        }
+        decode_message(&codes, p, key.group.bit_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let (pubkey, private_key, mut rng) = generate_key_pair_from_seed(&SEED, 64, 16);
+        let (elgamal_key, _) = crate::generate_pub_key(&SEED, 64, 16);
+        assert_eq!(elgamal_key.group.p, pubkey.group.p);
+        assert_eq!(pubkey, private_key.public_key());
+
+        for message in ["", "Cramer-Shoup", "a message of several chunks é中😀"] {
+            let cipher = String::from(message).encrypt(&pubkey, &mut rng).unwrap();
+            assert_eq!(Some(String::from(message)), cipher.decrypt(&private_key));
+        }
+        let (_, other_key) = generate_key_pair(&pubkey.group, &mut rng).unwrap();
+        let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+        assert_eq!(None, cipher.decrypt(&other_key));
+    }
+
+    #[test]
+    fn test_generator_outside_subgroup_is_rejected() {
+        // g of generate_pub_key is a primitive root, it generates all of Z_p^*
+        let (group_key, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        assert_eq!(
+            Err(CramerShoupError::InvalidGenerator),
+            generate_key_pair(&group_key.group, &mut rng)
+        );
+        let group = Arc::new(GroupParams::new(
+            group_key.group.p.clone(),
+            BigInt::from(1),
+            64,
+        ));
+        assert_eq!(
+            Err(CramerShoupError::InvalidGenerator),
+            generate_key_pair(&group, &mut rng)
+        );
+    }
+
+    #[test]
+    fn test_modified_ciphertexts_are_rejected() {
+        let (pubkey, private_key, mut rng) = generate_key_pair_from_seed(&SEED, 64, 16);
+        let p = &pubkey.group.p;
+        let cipher = String::from("do not malleate me")
+            .encrypt(&pubkey, &mut rng)
+            .unwrap();
+        let pairs = parse_cipher_pairs(&cipher).unwrap();
+        assert!(pairs.len() >= 4);
+
+        // e * g1, the attack that changes a plain elgamal plaintext undetectably
+        let mut mauled = pairs.clone();
+        mauled[1].0 = (&mauled[1].0 * &pubkey.group.g) % p;
+        assert_eq!(None, format_cipher_pairs(&mauled).decrypt(&private_key));
+        // rerandomized chunk with a matching v for the original alpha
+        let mut mauled = pairs.clone();
+        mauled[0].0 = (&mauled[0].0 * &pubkey.group.g) % p;
+        mauled[0].1 = (&mauled[0].1 * &pubkey.g2) % p;
+        assert_eq!(None, format_cipher_pairs(&mauled).decrypt(&private_key));
+        // swapped and dropped chunks
+        let mut mauled = pairs.clone();
+        mauled.swap(0, 2);
+        mauled.swap(1, 3);
+        assert_eq!(None, format_cipher_pairs(&mauled).decrypt(&private_key));
+        assert_eq!(None, format_cipher_pairs(&pairs[2..]).decrypt(&private_key));
+        assert_eq!(None, format_cipher_pairs(&pairs[1..]).decrypt(&private_key));
+    }
+}
@@ -0,0 +1,375 @@
+//! ec mod
+//! elgamal over elliptic curve groups: Ristretto255 with the `ristretto` feature and
+//! secp256k1 with the `secp256k1` feature. keys and ciphertexts are a few dozen bytes,
+//! and `Encryption`/`Decryption` are called as for `PublicKey`/`PrivateKey`.
+//! messages are chunked like `Encryption for String`, 30 bytes per point. a chunk is
+//! mapped to a point by try and increment: it fills 30 bytes of a candidate encoding and
+//! a counter the rest, until the encoding decompresses to a point.
+//! ciphertexts are "c d c d " with every point as lowercase hex of its compressed encoding.
+use crate::elgamal::{decode_utf16, encode_utf16};
+use crate::generic::{Decryption, Encryption};
+use core::fmt;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use rand_core::RngCore;
+
+/// Message bytes per point.
+pub const CHUNK_BYTES: usize = 30;
+
+/// Bit length handed to the chunk encoding, so that it packs `CHUNK_BYTES` per integer.
+const CHUNK_BITS: u32 = CHUNK_BYTES as u32 * 8 + 2;
+
+/// A prime order elliptic curve group with a fixed generator.
+pub trait Curve: Clone + PartialEq + fmt::Debug {
+    type Point: Clone + PartialEq + fmt::Debug;
+    type Scalar: Clone + PartialEq + fmt::Debug;
+
+    fn generator() -> Self::Point;
+    /// Uniform non-zero scalar.
+    fn random_scalar<R: RngCore>(rng: &mut R) -> Self::Scalar;
+    fn mul(point: &Self::Point, scalar: &Self::Scalar) -> Self::Point;
+    fn add(a: &Self::Point, b: &Self::Point) -> Self::Point;
+    fn sub(a: &Self::Point, b: &Self::Point) -> Self::Point;
+    /// Maps a chunk to a point, inverse of `decode_chunk`.
+    /// None if no candidate encoding is a point, which happens with negligible probability.
+    fn encode_chunk(chunk: &[u8; CHUNK_BYTES]) -> Option<Self::Point>;
+    fn decode_chunk(point: &Self::Point) -> Option<[u8; CHUNK_BYTES]>;
+    /// Compressed encoding.
+    fn to_bytes(point: &Self::Point) -> Vec<u8>;
+    /// Decodes a compressed encoding, None if it is not a point of the group.
+    fn from_bytes(bytes: &[u8]) -> Option<Self::Point>;
+    /// Canonical 32 byte encoding of a scalar.
+    fn scalar_to_bytes(scalar: &Self::Scalar) -> Vec<u8>;
+    /// Decodes a canonical encoding, None if it is not reduced mod the group order.
+    fn scalar_from_bytes(bytes: &[u8]) -> Option<Self::Scalar>;
+}
+
+/// Ristretto255, the prime order group over Curve25519.
+#[cfg(feature = "ristretto")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ristretto;
+
+/// secp256k1 in projective coordinates.
+#[cfg(feature = "secp256k1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1;
+
+/// Public key h = x * G on the curve C.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcPublicKey<C: Curve> {
+    pub h: C::Point,
+}
+
+/// Private key x on the curve C.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcPrivateKey<C: Curve> {
+    pub x: C::Scalar,
+}
+
+impl<C: Curve> EcPrivateKey<C> {
+    /// Computes the public key.
+    pub fn public_key(&self) -> EcPublicKey<C> {
+        EcPublicKey {
+            h: C::mul(&C::generator(), &self.x),
+        }
+    }
+}
+
+/// Generates a key pair with a random x.
+pub fn generate_key_pair<C: Curve, R: RngCore>(rng: &mut R) -> (EcPublicKey<C>, EcPrivateKey<C>) {
+    let private_key = EcPrivateKey {
+        x: C::random_scalar(rng),
+    };
+    (private_key.public_key(), private_key)
+}
+
+/// Generates a key pair from a seed, returns the rng to encrypt with, like `generate_pub_key`.
+///
+/// # Example
+///```rust
+/// # #[cfg(feature = "ristretto")] {
+/// use elgamal_wasm as elgamal;
+/// use elgamal::ec::{generate_key_pair_from_seed, Ristretto};
+/// use elgamal::generic::{Decryption, Encryption};
+/// let (pubkey, private_key, mut rng) = generate_key_pair_from_seed::<Ristretto>(&[2929]);
+/// let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+/// assert_eq!(Some(String::from("message")), cipher.decrypt(&private_key));
+/// # }
+/// ```
+pub fn generate_key_pair_from_seed<C: Curve>(
+    seed: &[u32],
+) -> (EcPublicKey<C>, EcPrivateKey<C>, mt19937::MT19937) {
+    let mut rng = mt19937::MT19937::new_with_slice_seed(seed);
+    let (pubkey, private_key) = generate_key_pair(&mut rng);
+    (pubkey, private_key, rng)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len().is_odd() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl<C: Curve> Encryption<C::Point, EcPublicKey<C>> for String {
+    /// Encrypts a string to an EC public key.
+    /// # Logic Desc
+    /// ```text
+    /// M[i] is the point of the ith chunk
+    /// y is a random scalar
+    /// c = y * G, d = M[i] + y * h
+    /// ```
+    /// Returns None if a chunk maps to no point.
+    fn encrypt<R: RngCore>(&self, key: &EcPublicKey<C>, rng: &mut R) -> Option<String> {
+        let mut cipher = String::new();
+        for chunk in encode_utf16(self, CHUNK_BITS) {
+            let mut bytes = [0u8; CHUNK_BYTES];
+            let le = chunk.to_bytes_le().1;
+            bytes[..le.len()].copy_from_slice(&le);
+            let m = C::encode_chunk(&bytes)?;
+            let y = C::random_scalar(rng);
+            let c = C::mul(&C::generator(), &y);
+            let d = C::add(&m, &C::mul(&key.h, &y));
+            cipher.push_str(&format!(
+                "{} {} ",
+                to_hex(&C::to_bytes(&c)),
+                to_hex(&C::to_bytes(&d))
+            ));
+        }
+        Some(cipher)
+    }
+}
+
+impl<C: Curve> Decryption<C::Point, EcPrivateKey<C>> for String {
+    /// Decrypts every pair to M = d - x * c and decodes the chunks.
+    fn decrypt(&self, key: &EcPrivateKey<C>) -> Option<String> {
+        let points = self
+            .split_whitespace()
+            .map(|hex| C::from_bytes(&from_hex(hex)?))
+            .collect::<Option<Vec<C::Point>>>()?;
+        if points.is_empty() || points.len().is_odd() {
+            return None;
+        }
+        let chunks = points
+            .chunks(2)
+            .map(|pair| {
+                let m = C::sub(&pair[1], &C::mul(&pair[0], &key.x));
+                let bytes = C::decode_chunk(&m)?;
+                Some(BigInt::from_bytes_le(Sign::Plus, &bytes))
+            })
+            .collect::<Option<Vec<BigInt>>>()?;
+        decode_utf16(&chunks, CHUNK_BITS)
+    }
+}
+
+#[cfg(feature = "ristretto")]
+mod ristretto {
+    use super::{Curve, Ristretto, CHUNK_BYTES};
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+    use curve25519_dalek::Scalar;
+    use rand_core::RngCore;
+
+    /// Candidate encodings are 2 * counter || chunk || 0x00: the even first byte keeps the
+    /// field element non-negative and the zero last byte keeps it below 2^255 - 19.
+    impl Curve for Ristretto {
+        type Point = RistrettoPoint;
+        type Scalar = Scalar;
+
+        fn generator() -> RistrettoPoint {
+            RISTRETTO_BASEPOINT_POINT
+        }
+
+        fn random_scalar<R: RngCore>(rng: &mut R) -> Scalar {
+            loop {
+                let mut wide = [0u8; 64];
+                rng.fill_bytes(&mut wide);
+                let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+                if scalar != Scalar::ZERO {
+                    return scalar;
+                }
+            }
+        }
+
+        fn mul(point: &RistrettoPoint, scalar: &Scalar) -> RistrettoPoint {
+            point * scalar
+        }
+
+        fn add(a: &RistrettoPoint, b: &RistrettoPoint) -> RistrettoPoint {
+            a + b
+        }
+
+        fn sub(a: &RistrettoPoint, b: &RistrettoPoint) -> RistrettoPoint {
+            a - b
+        }
+
+        /// About a quarter of the candidates decompress.
+        fn encode_chunk(chunk: &[u8; CHUNK_BYTES]) -> Option<RistrettoPoint> {
+            (0..128u8).find_map(|counter| {
+                let mut bytes = [0u8; 32];
+                bytes[0] = counter << 1;
+                bytes[1..31].copy_from_slice(chunk);
+                CompressedRistretto(bytes).decompress()
+            })
+        }
+
+        fn decode_chunk(point: &RistrettoPoint) -> Option<[u8; CHUNK_BYTES]> {
+            let bytes = point.compress().to_bytes();
+            if bytes[31] != 0 {
+                return None;
+            }
+            bytes[1..31].try_into().ok()
+        }
+
+        fn to_bytes(point: &RistrettoPoint) -> Vec<u8> {
+            point.compress().to_bytes().to_vec()
+        }
+
+        fn from_bytes(bytes: &[u8]) -> Option<RistrettoPoint> {
+            CompressedRistretto::from_slice(bytes).ok()?.decompress()
+        }
+
+        /// Little endian, as curve25519-dalek.
+        fn scalar_to_bytes(scalar: &Scalar) -> Vec<u8> {
+            scalar.to_bytes().to_vec()
+        }
+
+        fn scalar_from_bytes(bytes: &[u8]) -> Option<Scalar> {
+            Scalar::from_canonical_bytes(bytes.try_into().ok()?).into()
+        }
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+mod secp256k1 {
+    use super::{Curve, Secp256k1, CHUNK_BYTES};
+    use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+    use k256::elliptic_curve::PrimeField;
+    use k256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar};
+    use rand_core::RngCore;
+
+    fn decode_point(encoded: &EncodedPoint) -> Option<ProjectivePoint> {
+        let point: Option<AffinePoint> = AffinePoint::from_encoded_point(encoded).into();
+        point.map(ProjectivePoint::from)
+    }
+
+    /// Candidate x coordinates are 0x00 || chunk || counter, with the even y.
+    impl Curve for Secp256k1 {
+        type Point = ProjectivePoint;
+        type Scalar = Scalar;
+
+        fn generator() -> ProjectivePoint {
+            ProjectivePoint::GENERATOR
+        }
+
+        fn random_scalar<R: RngCore>(rng: &mut R) -> Scalar {
+            loop {
+                let mut bytes = [0u8; 32];
+                rng.fill_bytes(&mut bytes);
+                let scalar: Option<Scalar> = Scalar::from_repr(bytes.into()).into();
+                match scalar {
+                    Some(scalar) if scalar != Scalar::ZERO => return scalar,
+                    _ => continue,
+                }
+            }
+        }
+
+        fn mul(point: &ProjectivePoint, scalar: &Scalar) -> ProjectivePoint {
+            point * scalar
+        }
+
+        fn add(a: &ProjectivePoint, b: &ProjectivePoint) -> ProjectivePoint {
+            a + b
+        }
+
+        fn sub(a: &ProjectivePoint, b: &ProjectivePoint) -> ProjectivePoint {
+            a - b
+        }
+
+        /// About half of the candidates are x coordinates.
+        fn encode_chunk(chunk: &[u8; CHUNK_BYTES]) -> Option<ProjectivePoint> {
+            (0..=255u8).find_map(|counter| {
+                let mut bytes = [0u8; 33];
+                bytes[0] = 0x02;
+                bytes[2..32].copy_from_slice(chunk);
+                bytes[32] = counter;
+                decode_point(&EncodedPoint::from_bytes(bytes).ok()?)
+            })
+        }
+
+        fn decode_chunk(point: &ProjectivePoint) -> Option<[u8; CHUNK_BYTES]> {
+            let encoded = point.to_affine().to_encoded_point(true);
+            let x = encoded.x()?;
+            if x[0] != 0 {
+                return None;
+            }
+            x[1..31].try_into().ok()
+        }
+
+        fn to_bytes(point: &ProjectivePoint) -> Vec<u8> {
+            point.to_affine().to_encoded_point(true).as_bytes().to_vec()
+        }
+
+        fn from_bytes(bytes: &[u8]) -> Option<ProjectivePoint> {
+            decode_point(&EncodedPoint::from_bytes(bytes).ok()?)
+        }
+
+        /// Big endian, as SEC 1.
+        fn scalar_to_bytes(scalar: &Scalar) -> Vec<u8> {
+            scalar.to_bytes().to_vec()
+        }
+
+        fn scalar_from_bytes(bytes: &[u8]) -> Option<Scalar> {
+            let bytes: [u8; 32] = bytes.try_into().ok()?;
+            Scalar::from_repr(bytes.into()).into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u32; 1] = [2929];
+
+    /// Round trips on the curve C, with points of point_len bytes.
+    fn check_curve<C: Curve>(point_len: usize) {
+        let (pubkey, private_key, mut rng) = generate_key_pair_from_seed::<C>(&SEED);
+        let (same_pubkey, _, _) = generate_key_pair_from_seed::<C>(&SEED);
+        assert_eq!(pubkey, same_pubkey);
+        assert_eq!(pubkey, private_key.public_key());
+
+        for message in ["", "message", "a message of several points é中😀 and more"] {
+            let cipher = String::from(message).encrypt(&pubkey, &mut rng).unwrap();
+            assert_eq!(Some(String::from(message)), cipher.decrypt(&private_key));
+        }
+        let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+        let points: Vec<&str> = cipher.split_whitespace().collect();
+        assert_eq!(2, points.len());
+        assert!(points.iter().all(|point| point.len() == 2 * point_len));
+
+        let (_, other_key) = generate_key_pair::<C, _>(&mut rng);
+        assert_eq!(None, cipher.decrypt(&other_key));
+        assert_eq!(None, points[0].to_string().decrypt(&private_key));
+        assert_eq!(None, "zz zz ".to_string().decrypt(&private_key));
+    }
+
+    #[cfg(feature = "ristretto")]
+    #[test]
+    fn test_ristretto() {
+        check_curve::<Ristretto>(32);
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_secp256k1() {
+        check_curve::<Secp256k1>(33);
+    }
+}
@@ -1,7 +1,8 @@
 //! elgamal mod
 //! this is a utils for elgamal security algorithm
 //! use for generating public_key
-use crate::generic::{Decryption, Encryption, PrivateKey, PublicKey};
+use crate::generic::{Decryption, Encryption, ParseError, PrivateKey, PublicKey};
+use crate::groups::GroupParams;
 use crate::utils;
 use codec::{Decode, Encode};
 use encoding::all::UTF_16LE;
@@ -9,12 +10,19 @@ use encoding::{DecoderTrap, EncoderTrap, Encoding};
 use mt19937;
 use num_bigint::{BigInt, Sign};
 use num_integer::Integer;
-use num_traits::{Num, ToPrimitive};
+use num_traits::Num;
 use rand_core::RngCore;
+use sha2::{Digest, Sha256};
 use sp_core::U256;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
 pub type KeyWithRng = (PublicKey<BigInt>, mt19937::MT19937);
 
+/// Radix used to print and parse cipher pairs.
+pub(crate) const STR_RADIX: u32 = 10;
+
 /// Generate a seed data slice from a key data.
 pub trait Seed {
     fn yield_seed_slice(&self) -> Vec<u32>;
@@ -22,12 +30,67 @@ pub trait Seed {
 
 impl Seed for PublicKey<BigInt> {
     fn yield_seed_slice(&self) -> Vec<u32> {
-        let sum = &self.p + &self.h + &self.g;
+        let sum = &self.group.p + &self.h + &self.group.g;
         sum.to_u32_digits().1
     }
 }
 
+/// Version of the seed derivation used to walk the key chain.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, Debug)]
+pub enum SeedVersion {
+    /// The u32 digits of p + g + h, external entropy is ignored. Kept for existing chains.
+    V0,
+    /// SHA-256 of a domain tag, the key's p, g, h and bit_length and the external entropy.
+    V1,
+}
+
+/// Derive the next seed from a key and external entropy, e.g. a block hash or VRF output.
+pub trait VersionedSeed {
+    fn derive_seed(&self, version: SeedVersion, entropy: &[u8]) -> Vec<u32>;
+}
+
+/// Same seeds as the `PublicKey<BigInt>` of the raw key.
+impl VersionedSeed for RawPublicKey {
+    fn derive_seed(&self, version: SeedVersion, entropy: &[u8]) -> Vec<u32> {
+        PublicKey::<BigInt>::from_raw(self.clone()).derive_seed(version, entropy)
+    }
+}
+
+impl VersionedSeed for PublicKey<BigInt> {
+    /// # Logic Desc
+    /// ```text
+    /// v0: seed = u32 digits of (p + g + h)
+    /// v1: seed = u32 words of SHA-256("elgamal_wasm/seed/v1" || len(p) || p || len(g) || g
+    ///            || len(h) || h || bit_length || len(entropy) || entropy)
+    /// ```
+    /// p, g and h are little endian bytes, lengths are u64 and bit_length u32 little endian,
+    /// so keys of any size are accepted.
+    fn derive_seed(&self, version: SeedVersion, entropy: &[u8]) -> Vec<u32> {
+        match version {
+            SeedVersion::V0 => self.yield_seed_slice(),
+            SeedVersion::V1 => {
+                let mut hasher = Sha256::new();
+                hasher.update(b"elgamal_wasm/seed/v1");
+                for value in [&self.group.p, &self.group.g, &self.h] {
+                    let bytes = value.to_bytes_le().1;
+                    hasher.update((bytes.len() as u64).to_le_bytes());
+                    hasher.update(&bytes);
+                }
+                hasher.update(self.group.bit_length.to_le_bytes());
+                hasher.update((entropy.len() as u64).to_le_bytes());
+                hasher.update(entropy);
+                hasher
+                    .finalize()
+                    .chunks(4)
+                    .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+                    .collect()
+            }
+        }
+    }
+}
+
 /// The raw public key type use bytes string.
+/// `groups::RawGroupPublicKey` is the opt-in form that refers to a registered group by id.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
 pub struct RawPublicKey {
     pub p: U256,
@@ -45,27 +108,22 @@ pub trait RawKey {
 impl RawKey for PublicKey<BigInt> {
     fn to_raw(self) -> RawPublicKey {
         RawPublicKey {
-            p: U256::from_little_endian(self.p.to_bytes_le().1.as_slice()),
-            g: U256::from_little_endian(self.g.to_bytes_le().1.as_slice()),
+            p: U256::from_little_endian(self.group.p.to_bytes_le().1.as_slice()),
+            g: U256::from_little_endian(self.group.g.to_bytes_le().1.as_slice()),
             h: U256::from_little_endian(self.h.to_bytes_le().1.as_slice()),
-            bit_length: self.bit_length,
+            bit_length: self.group.bit_length,
         }
     }
 
     fn from_raw(raw_key: RawPublicKey) -> Self {
         let mut num: [u8; 32] = [0u8; 32];
         raw_key.p.to_little_endian(&mut num);
-        let p = BigInt::from_bytes_le(Sign::Plus, &num.to_vec());
+        let p = BigInt::from_bytes_le(Sign::Plus, &num);
         raw_key.g.to_little_endian(&mut num);
-        let g = BigInt::from_bytes_le(Sign::Plus, &num.to_vec());
+        let g = BigInt::from_bytes_le(Sign::Plus, &num);
         raw_key.h.to_little_endian(&mut num);
-        let h = BigInt::from_bytes_le(Sign::Plus, &num.to_vec());
-        PublicKey::<BigInt> {
-            p,
-            g,
-            h,
-            bit_length: raw_key.bit_length,
-        }
+        let h = BigInt::from_bytes_le(Sign::Plus, &num);
+        Arc::new(GroupParams::new(p, g, raw_key.bit_length)).public_key(h)
     }
 }
 
@@ -85,15 +143,98 @@ pub fn generate_pub_key(seed: &[u32], bit_length: u32, i_confidence: u32) -> Key
     let val1 = utils::find_primitive_root_bigint(&val, &mut rng);
     let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(&seed);
     let val2 = utils::find_h_bigint(&val, &mut rng);
-    let pubkey: PublicKey<BigInt> = PublicKey {
-        p: val,
-        g: val1,
-        h: val2,
-        bit_length,
-    };
+    let pubkey = Arc::new(GroupParams::new(val, val1, bit_length)).public_key(val2);
+    (pubkey, rng)
+}
+
+/// Expand one seed into the independent MT19937 seed of a key generation stream.
+/// # Logic Desc
+/// ```text
+/// stream seed = u32 words of SHA-256("elgamal_wasm/keygen/v2" || len(label) || label || seed)
+/// ```
+fn stream_seed(seed: &[u32], label: &[u8]) -> Vec<u32> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"elgamal_wasm/keygen/v2");
+    hasher.update((label.len() as u64).to_le_bytes());
+    hasher.update(label);
+    for word in seed {
+        hasher.update(word.to_le_bytes());
+    }
+    hasher
+        .finalize()
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect()
+}
+
+///generate public_key with seed、bit_length、i_confidence from domain separated random streams
+///
+/// Unlike `generate_pub_key`, which reuses the same MT19937 stream for p, g and h,
+/// the seed is expanded with SHA-256 into independent streams for each component and
+/// for the returned rng.
+/// # Logic Desc
+/// ```text
+/// p is the prime, drawn from the stream labelled "p"
+/// g is the primitive root, drawn from the stream labelled "g"
+/// h is random in (1, p-1) inclusive, drawn from the stream labelled "h"
+/// the returned rng is the stream labelled "rng"
+/// ```
+pub fn generate_pub_key_v2(seed: &[u32], bit_length: u32, i_confidence: u32) -> KeyWithRng {
+    let mut rng = mt19937::MT19937::new_with_slice_seed(&stream_seed(seed, b"p"));
+    let val = utils::random_prime_bigint(bit_length, i_confidence, &mut rng);
+    let mut rng = mt19937::MT19937::new_with_slice_seed(&stream_seed(seed, b"g"));
+    let val1 = utils::find_primitive_root_bigint(&val, &mut rng);
+    let mut rng = mt19937::MT19937::new_with_slice_seed(&stream_seed(seed, b"h"));
+    let val2 = utils::find_h_bigint(&val, &mut rng);
+    let pubkey = Arc::new(GroupParams::new(val, val1, bit_length)).public_key(val2);
+    let rng = mt19937::MT19937::new_with_slice_seed(&stream_seed(seed, b"rng"));
     (pubkey, rng)
 }
 
+///generate public_key in the quadratic residue subgroup with seed、bit_length、i_confidence
+///
+/// Works like `generate_pub_key`, but g generates the order q subgroup of quadratic residues
+/// of the safe prime p = 2q + 1 and h is a quadratic residue too. Together with the
+/// quadratic residue message encoding, encryption is IND-CPA secure under DDH.
+/// # Logic Desc
+/// ```text
+/// p = 2q + 1 is the safe prime
+/// g = a^2 mod p for random a, g != 1
+/// x is random in (1, q-1) inclusive
+/// h = g ^ x mod p
+/// ```
+pub fn generate_qr_pub_key(seed: &[u32], bit_length: u32, i_confidence: u32) -> KeyWithRng {
+    let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(seed);
+    let val = utils::random_prime_bigint(bit_length, i_confidence, &mut rng);
+    let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(seed);
+    let val1 = utils::find_qr_generator_bigint(&val, &mut rng);
+    let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(seed);
+    let val2 = utils::find_qr_h_bigint(&val, &mut rng);
+    let pubkey = Arc::new(GroupParams::new(val, val1, bit_length)).public_key(val2);
+    (pubkey, rng)
+}
+
+/// Generates a key pair on the group with x random in [1, q - 1] where p = 2q + 1.
+/// Both keys share the group.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::generic::{Decryption, Encryption};
+/// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 32, 32);
+/// let (pubkey, private_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+/// assert_eq!(Some(String::from("message")), cipher.decrypt(&private_key));
+/// ```
+pub fn generate_key_pair<R: RngCore>(
+    group: &Arc<GroupParams>,
+    rng: &mut R,
+) -> (PublicKey<BigInt>, PrivateKey<BigInt>) {
+    let x = utils::gen_bigint_range(rng, &BigInt::from(1), &(&group.q - 1u32));
+    let h = group.g.modpow(&x, &group.p);
+    (group.public_key(h), group.private_key(x))
+}
+
 impl Encryption<BigInt> for String {
     ///Encrypts a string using the public key k.
     ///
@@ -107,170 +248,298 @@ impl Encryption<BigInt> for String {
     /// let pubkey = tuple.0;
     /// let msg = String::from("message for encrypt");
     /// let mut rng: mt19937::MT19937 = tuple.1;
-    /// let result = msg.encrypt(&pubkey, &mut rng);
+    /// let result = msg.encrypt(&pubkey, &mut rng).unwrap();
     /// ```
     /// # Logic Desc
     /// ```text
-    /// if n = 24, k = n / 8 = 3
-    /// z[0] = (summation from i = 0 to i = k)m[i]*(2^(8*i))
+    /// if n = 26, k = (n - 2) / 8 = 3
+    /// z[0] = (summation from i = 0 to i = k - 1)m[i]*(2^(8*i))
     /// where m[i] is the ith message byte
+    /// z[0] is then mapped to a quadratic residue mod p before encryption
     /// ```
-    fn encrypt<R: RngCore>(&self, key: &PublicKey<BigInt>, rng: &mut R) -> String {
-        let z = encode_utf16(self, key.bit_length);
+    fn encrypt<R: RngCore>(&self, key: &PublicKey<BigInt>, rng: &mut R) -> Option<String> {
+        let z = encode_message(self, key)?;
         // cipher_pairs list will hold pairs (c, d) corresponding to each integer in z
-        let mut cipher_pairs = vec![];
-        // i is an integer in z
-        for i_code in z {
-            // pick random y from (0, p-1) inclusive
-            let y = utils::gen_bigint_range(rng, &BigInt::from(0), &key.p);
-            // c = g^y mod p
-            let c = key.g.modpow(&y, &key.p);
-            // d = ih^y mod p
-            let d = (&i_code * key.h.modpow(&y, &key.p)) % &key.p;
-            // add the pair to the cipher pairs list
-            let mut arr: Vec<BigInt> = Vec::new();
-            arr.push(c);
-            arr.push(d);
-            cipher_pairs.push(arr);
-        }
-        let mut encrypted_str = "".to_string();
-        let space = " ".to_string();
-        for pair in cipher_pairs {
-            let pair_one = pair[0].to_str_radix(10).to_string();
-            let pair_two = pair[1].to_str_radix(10).to_string();
-            encrypted_str += &pair_one;
-            encrypted_str += &space;
-            encrypted_str += &pair_two;
-            encrypted_str += &space;
-        }
-        encrypted_str
+        let p = &key.group.p;
+        let cipher_pairs = encrypt_codes(&z, p, rng, |y| {
+            (key.group.g.modpow(y, p), key.h.modpow(y, p))
+        });
+        Some(format_cipher_pairs(&cipher_pairs))
+    }
+}
+
+/// Encrypts encoded integers into cipher pairs (c, d).
+///
+/// `pow_g_h` maps a random exponent y to (g^y mod p, h^y mod p), so callers can plug in
+/// plain modular exponentiation or precomputed fixed-base tables.
+pub(crate) fn encrypt_codes<R, F>(
+    codes: &[BigInt],
+    p: &BigInt,
+    rng: &mut R,
+    mut pow_g_h: F,
+) -> Vec<(BigInt, BigInt)>
+where
+    R: RngCore,
+    F: FnMut(&BigInt) -> (BigInt, BigInt),
+{
+    let mut cipher_pairs = Vec::with_capacity(codes.len());
+    // i is an integer in z
+    for i_code in codes {
+        let y = random_exponent(rng, p);
+        // c = g^y mod p
+        let (c, h_y) = pow_g_h(&y);
+        // d = ih^y mod p
+        let d = (i_code * h_y) % p;
+        // add the pair to the cipher pairs list
+        cipher_pairs.push((c, d));
     }
+    cipher_pairs
+}
+
+/// Pick the random exponent y of one cipher pair from [0, p].
+pub(crate) fn random_exponent<R: RngCore>(rng: &mut R, p: &BigInt) -> BigInt {
+    utils::gen_bigint_range(rng, &BigInt::from(0), p)
+}
+
+/// Prints cipher pairs as space separated integers, the format parsed by `Decryption`.
+pub(crate) fn format_cipher_pairs(cipher_pairs: &[(BigInt, BigInt)]) -> String {
+    let mut encrypted_str = "".to_string();
+    let space = " ".to_string();
+    for (c, d) in cipher_pairs {
+        encrypted_str += &c.to_str_radix(STR_RADIX);
+        encrypted_str += &space;
+        encrypted_str += &d.to_str_radix(STR_RADIX);
+        encrypted_str += &space;
+    }
+    encrypted_str
 }
 
 impl Decryption<BigInt> for String {
     ///Performs decryption on the cipher pairs found in Cipher using
     ///private key K2 and writes the decrypted values to file Plaintext.
     fn decrypt(&self,key: &PrivateKey<BigInt>) -> Option<String> {
-        // check if the last char is space
-        let mut cipher_chars = self.chars();
-        if let Some(last) = cipher_chars.clone().last() {
-            if last.is_whitespace() {
-                // if the last char is space, removed it from the string.
-                cipher_chars.next_back();
-            }
-        } else {
-            // if the cipher string is empty, return None.
-            return None;
-        }
-        let reduced_str = cipher_chars.as_str();
-        let ciphers = reduced_str.split(" ").collect::<Vec<&str>>();
-
-        let count = ciphers.len();
-        if count % 2 != 0 {
-            return None;
-        }
+        let cipher_pairs = parse_cipher_pairs(self)?;
         let mut plain_text = Vec::new();
-        for cd in ciphers.chunks(2) {
-            // c = first number in pair
-            let c = cd[0];
-            let c_int = BigInt::from_str_radix(c, STR_RADIX).unwrap();
-            // d = second number in pair
-            let d = cd[1];
-            let d_int = BigInt::from_str_radix(d, STR_RADIX).unwrap();
+        for (c_int, d_int) in cipher_pairs {
             // s = c^x mod p
-            let s = c_int.modpow(&key.x, &key.p);
+            let s = c_int.modpow(&key.x, &key.group.p);
             // plaintext integer = ds^-1 mod p
-            let p_2 = &key.p - BigInt::from(2);
-            let mod_exp_s = s.modpow(&p_2, &key.p);
-            let d_by_mod = &d_int * mod_exp_s;
-            let plain_i = d_by_mod.mod_floor(&key.p);
+            let plain_i = unblind(&d_int, &s, &key.group.p);
             // add plain to list of plaintext integers
             plain_text.push(plain_i);
-            // count the length of the cipher strings
         }
-        Some(decode_utf16(&plain_text, key.bit_length.clone()))
+        decode_message(&plain_text, &key.group.p, key.group.bit_length)
+    }
+}
+
+/// Parses the cipher pairs (c, d) printed by `Encryption`.
+/// Returns None if the string is empty, has an odd count of numbers or a malformed number.
+pub(crate) fn parse_cipher_pairs(cipher: &str) -> Option<Vec<(BigInt, BigInt)>> {
+    // check if the last char is space, the empty cipher string returns None.
+    let mut cipher_chars = cipher.chars();
+    let last = cipher_chars.clone().last()?;
+    if last.is_whitespace() {
+        // if the last char is space, removed it from the string.
+        cipher_chars.next_back();
     }
+    let reduced_str = cipher_chars.as_str();
+    let ciphers = reduced_str.split(" ").collect::<Vec<&str>>();
+
+    let count = ciphers.len();
+    if count.is_odd() {
+        return None;
+    }
+    let mut cipher_pairs = Vec::with_capacity(count / 2);
+    for cd in ciphers.chunks(2) {
+        // c = first number in pair
+        let c_int = BigInt::from_str_radix(cd[0], STR_RADIX).ok()?;
+        // d = second number in pair
+        let d_int = BigInt::from_str_radix(cd[1], STR_RADIX).ok()?;
+        cipher_pairs.push((c_int, d_int));
+    }
+    Some(cipher_pairs)
+}
+
+/// Parsed ciphertext, the cipher pairs (c, d) of the string printed by `Encryption`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ciphertext {
+    pub pairs: Vec<(BigInt, BigInt)>,
+}
+
+impl Ciphertext {
+    /// Parses a ciphertext string, None if it is malformed.
+    pub fn parse(cipher: &str) -> Option<Self> {
+        parse_cipher_pairs(cipher).map(|pairs| Ciphertext { pairs })
+    }
+
+    /// The ciphertext string accepted by `Decryption`.
+    pub fn to_cipher_string(&self) -> String {
+        format_cipher_pairs(&self.pairs)
+    }
+}
+
+/// Prints the ciphertext string, same as `to_cipher_string`.
+impl fmt::Display for Ciphertext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_cipher_string())
+    }
+}
+
+impl FromStr for Ciphertext {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ciphertext::parse(s).ok_or(ParseError)
+    }
+}
+
+/// Removes the mask s = c^x from d, returns d * s^-1 mod p.
+pub(crate) fn unblind(d: &BigInt, s: &BigInt, p: &BigInt) -> BigInt {
+    let p_2 = p - BigInt::from(2);
+    let mod_exp_s = s.modpow(&p_2, p);
+    let d_by_mod = d * mod_exp_s;
+    d_by_mod.mod_floor(p)
+}
+
+/// Byte order mark put in front of the UTF-16LE plaintext bytes.
+pub(crate) const BOM: [u8; 2] = [255, 254];
+
+/// Marks the end of the message bytes, the last chunk is filled up with zero bytes after it.
+pub(crate) const PAD_MARKER: u8 = 0x80;
+
+/// Number of message bytes packed into one integer.
+/// Two bits of headroom keep every chunk + 1 within [1, q] for the safe prime
+/// p = 2q + 1 in [2^(n-1)+1, 2^n+1], so it can be mapped into the QR subgroup.
+pub(crate) fn chunk_size(bit_length: u32) -> usize {
+    (bit_length.saturating_sub(2) / 8) as usize
 }
 
-/// Encodes bytes to integers mod p.
+/// Encodes a string to integers mod p.
 /// # Logic Desc
 /// ```text
-/// if n = 24, k = n / 8 = 3
-/// z[0] = (summation from i = 0 to i = k)m[i]*(2^(8*i))
+/// m = BOM || UTF_16LE(plaintext) || 0x80 || 0x00 ...
+/// if n = 26, k = (n - 2) / 8 = 3
+/// z[j] = (summation from i = 0 to i = k - 1)m[j*k + i]*(2^(8*i))
 /// where m[i] is the ith message byte
 /// ```
-fn encode_utf16(plaintext: &str, bit_length: u32) -> Vec<BigInt> {
-    let mut byte_array: Vec<u8> = UTF_16LE.encode(plaintext, EncoderTrap::Strict).unwrap();
-    byte_array.insert(0, 254);
-    byte_array.insert(0, 255);
-
-    // z is the array of integers mod p
-    let mut z: Vec<BigInt> = vec![];
-    // each encoded integer will be a linear combination of k message bytes
-    // k must be the number of bits in the prime divided by 8 because each
-    // message byte is 8 bits long
-    let k: isize = (bit_length / 8) as isize;
-    // j marks the jth encoded integer
-    // j will start at 0 but make it -k because j will be incremented during first iteration
-    let mut j: isize = -1 * k;
-    // num is the summation of the message bytes
-    // num = 0
-    // i iterates through byte array
-    for idx in 0..byte_array.len() {
-        // if i is divisible by k, start a new encoded integer
-        if idx as isize % k == 0 {
-            j += k;
-            // num = 0
-            z.push(BigInt::from(0));
+pub(crate) fn encode_utf16(plaintext: &str, bit_length: u32) -> Vec<BigInt> {
+    let mut byte_array: Vec<u8> = BOM.to_vec();
+    byte_array.extend(UTF_16LE.encode(plaintext, EncoderTrap::Strict).unwrap());
+    pack_bytes(&byte_array, chunk_size(bit_length))
+}
+
+/// Packs bytes into integers of k little endian bytes each.
+/// The bytes are terminated by `PAD_MARKER` and zero padded to a multiple of k,
+/// so `unpack_bytes` recovers them exactly, trailing zero bytes included.
+pub(crate) fn pack_bytes(bytes: &[u8], k: usize) -> Vec<BigInt> {
+    assert!(k > 0, "bit_length must be at least 10");
+    let mut padded = Vec::with_capacity(bytes.len() + k);
+    padded.extend_from_slice(bytes);
+    padded.push(PAD_MARKER);
+    padded.resize(padded.len().div_ceil(k) * k, 0);
+    padded
+        .chunks(k)
+        .map(|chunk| BigInt::from_bytes_le(Sign::Plus, chunk))
+        .collect()
+}
+
+/// Unpacks integers of k little endian bytes each and strips the padding added by `pack_bytes`.
+/// Returns None if an integer does not fit in k bytes or the padding is malformed.
+pub(crate) fn unpack_bytes(encoded_ints: &[BigInt], k: usize) -> Option<Vec<u8>> {
+    if k == 0 {
+        return None;
+    }
+    let mut byte_array: Vec<u8> = Vec::with_capacity(encoded_ints.len() * k);
+    for num in encoded_ints {
+        let (sign, mut bytes) = num.to_bytes_le();
+        if sign == Sign::Minus || bytes.len() > k {
+            return None;
         }
-        let index: usize = (j / k) as usize;
-        let base: BigInt = BigInt::from(2);
-        let mi: u32 = (8 * (idx as isize % k)) as u32;
-        // add the byte multiplied by 2 raised to a multiple of 8
-        z[index] += BigInt::from(byte_array[idx] as i64) * base.pow(mi);
+        bytes.resize(k, 0);
+        byte_array.extend(bytes);
+    }
+    while byte_array.last() == Some(&0) {
+        byte_array.pop();
+    }
+    match byte_array.pop() {
+        Some(PAD_MARKER) => Some(byte_array),
+        _ => None,
     }
-    z
 }
 
-///Decodes integers to the original message bytes.
+///Decodes integers to the original message.
 ///
 ///# Example:
 /// ```text
-/// if "You" were encoded.
-/// Letter        #ASCII
-/// Y              89
-/// o              111
-/// u              117
-/// if the encoded integer is 7696217 and k = 3
-/// m[0] = 7696217 % 256 % 65536 / (2^(8*0)) = 89 = 'Y'
-/// 7696217 - (89 * (2^(8*0))) = 7696128
-/// m[1] = 7696128 % 65536 / (2^(8*1)) = 111 = 'o'
-/// 7696128 - (111 * (2^(8*1))) = 7667712
-/// m[2] = 7667712 / (2^(8*2)) = 117 = 'u'
+/// if "You" were encoded and k = 3, the bytes are
+/// FF FE 59 00 6F 00 75 00 80
+/// each integer holds k bytes in little endian order
+/// z[0] = 0xFF + 0xFE * 2^8 + 0x59 * 2^16
+/// to_bytes_le(z[0]) = FF FE 59
 /// ```
-pub fn decode_utf16(encoded_ints: &Vec<BigInt>, bit_length: u32) -> String {
+pub fn decode_utf16(encoded_ints: &[BigInt], bit_length: u32) -> Option<String> {
     // bytes vector will hold the decoded original message bytes
-    let mut byte_array: Vec<u8> = Vec::new();
-    // each encoded integer is a linear combination of k message bytes
-    // k must be the number of bits in the prime divided by 8 because each
-    // message byte is 8 bits long
-    let k = bit_length / 8;
-    for num in encoded_ints {
-        let mut temp = num.clone();
-        for i in 0..k {
-            let idx_1 = i + 1;
-            for j in idx_1..k {
-                temp = temp.mod_floor(&BigInt::from(2^(8 * j)));
-            }
-            let two_pow_i = 2^(8 * i);
-            let letter = BigInt::from(&temp / &two_pow_i).to_u8().unwrap();
-            byte_array.push(letter);
-            temp = num - (&(letter as u32) * two_pow_i);
-        }
-    }
-    let raw_text = UTF_16LE.decode(&byte_array, DecoderTrap::Strict).unwrap();
+    let byte_array = unpack_bytes(encoded_ints, chunk_size(bit_length))?;
+    let raw_text = UTF_16LE.decode(&byte_array, DecoderTrap::Strict).ok()?;
     // remove the byte order mark (BOM)
-    let stripped_text = raw_text.strip_prefix("\u{feff}").unwrap();
-    stripped_text.to_string()
+    let stripped_text = raw_text.strip_prefix("\u{feff}")?;
+    Some(stripped_text.to_string())
 }
 
+/// Maps a chunk into the quadratic residue subgroup of the safe prime p = 2q + 1.
+/// The group must pass `GroupParams::encodes_messages`, callers check it once per key.
+/// # Logic Desc
+/// ```text
+/// m = chunk + 1, so 1 <= m <= q
+/// p = 3 mod 4, so -1 is a non-residue and exactly one of m, p - m is a residue
+/// encoded = m if m^q = 1 mod p else p - m
+/// ```
+pub(crate) fn encode_to_group(chunk: &BigInt, p: &BigInt) -> BigInt {
+    let q: BigInt = (p - 1u32) / 2u32;
+    let m = chunk + 1u32;
+    debug_assert!(m <= q, "the group is checked with encodes_messages");
+    if m.modpow(&q, p) == BigInt::from(1) {
+        m
+    } else {
+        p - m
+    }
+}
+
+/// Inverse of `encode_to_group`, returns None for elements outside [1, p - 1].
+pub(crate) fn decode_from_group(encoded: &BigInt, p: &BigInt) -> Option<BigInt> {
+    let q: BigInt = (p - 1u32) / 2u32;
+    if encoded < &BigInt::from(1) || encoded >= p {
+        return None;
+    }
+    if encoded <= &q {
+        Some(encoded - 1u32)
+    } else {
+        Some(p - encoded - 1u32)
+    }
+}
+
+/// Encodes a message into quadratic residues mod p, ready to be encrypted.
+/// Returns None if the key cannot encode messages.
+pub(crate) fn encode_message(plaintext: &str, key: &PublicKey<BigInt>) -> Option<Vec<BigInt>> {
+    if !key.group.encodes_messages() {
+        return None;
+    }
+    let codes = encode_utf16(plaintext, key.group.bit_length)
+        .iter()
+        .map(|chunk| encode_to_group(chunk, &key.group.p))
+        .collect();
+    Some(codes)
+}
+
+/// Decodes decrypted quadratic residues mod p back to the message.
+pub(crate) fn decode_message(
+    encoded_ints: &[BigInt],
+    p: &BigInt,
+    bit_length: u32,
+) -> Option<String> {
+    let chunks = encoded_ints
+        .iter()
+        .map(|encoded| decode_from_group(encoded, p))
+        .collect::<Option<Vec<BigInt>>>()?;
+    decode_utf16(&chunks, bit_length)
+}
@@ -0,0 +1,293 @@
+//! envelope mod
+//! multi-recipient encryption: the payload is sealed once with ChaCha20-Poly1305
+//! under a random payload key, and every recipient gets a slot holding that key
+//! wrapped by an elgamal key encapsulation in the recipient's own group.
+//! each slot draws its own exponent, so recipients may be on different groups.
+//! the payload is authenticated together with every slot, so a changed slot is
+//! detected by all recipients, not only the one it belongs to.
+use crate::generic::{PrivateKey, PublicKey};
+use crate::groups::{decode_bigint, encode_bigint};
+use crate::proofs::in_group;
+use crate::utils;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use codec::{Decode, Encode, Error, Input, Output};
+use num_bigint::BigInt;
+use rand_core::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Fingerprint of a recipient public key, see `recipient_id`.
+pub type RecipientId = [u8; 32];
+
+/// Errors of sealing and opening envelopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// `seal` was given no recipients.
+    NoRecipients,
+    /// No slot of the envelope is addressed to this key.
+    NoSlot,
+    /// The slot or the payload failed authentication, it was changed or is malformed.
+    InvalidCiphertext,
+}
+
+/// Type alias for envelope results.
+pub type EnvelopeResult<T> = std::result::Result<T, EnvelopeError>;
+
+/// The payload key wrapped for one recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub recipient: RecipientId,
+    /// g^y of the key encapsulation, y in [1, q - 1].
+    pub c: BigInt,
+    /// The payload key xor a key derived from h^y.
+    pub wrapped_key: [u8; 32],
+}
+
+/// SCALE encodes the recipient, c as a little endian byte vector and the wrapped key.
+impl Encode for Slot {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.recipient.encode_to(dest);
+        encode_bigint(&self.c, dest);
+        self.wrapped_key.encode_to(dest);
+    }
+}
+
+impl Decode for Slot {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        Ok(Slot {
+            recipient: RecipientId::decode(input)?,
+            c: decode_bigint(input)?,
+            wrapped_key: <[u8; 32]>::decode(input)?,
+        })
+    }
+}
+
+/// A payload encrypted once and readable by the holder of any slot's private key.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Envelope {
+    pub slots: Vec<Slot>,
+    /// ChaCha20-Poly1305 ciphertext and tag of the payload.
+    pub payload: Vec<u8>,
+}
+
+/// SHA-256 of the domain tag, the group id and h, identifying a public key across groups.
+pub fn recipient_id(key: &PublicKey<BigInt>) -> RecipientId {
+    let h = key.h.to_bytes_le().1;
+    let mut hasher = Sha256::new();
+    hasher.update(b"elgamal_wasm/envelope/recipient");
+    hasher.update(key.group.id());
+    hasher.update((h.len() as u64).to_le_bytes());
+    hasher.update(&h);
+    hasher.finalize().into()
+}
+
+/// Key wrapping the payload key, hashed from the slot's recipient, c and shared secret h^y.
+fn wrapping_key(recipient: &RecipientId, c: &BigInt, shared: &BigInt) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"elgamal_wasm/envelope/wrap");
+    hasher.update(recipient);
+    for element in [c, shared] {
+        let bytes = element.to_bytes_le().1;
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+/// Associated data of the payload, binding it to every slot in order.
+fn slots_digest(slots: &[Slot]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"elgamal_wasm/envelope/slots");
+    hasher.update((slots.len() as u64).to_le_bytes());
+    for slot in slots {
+        let c = slot.c.to_bytes_le().1;
+        hasher.update(slot.recipient);
+        hasher.update((c.len() as u64).to_le_bytes());
+        hasher.update(&c);
+        hasher.update(slot.wrapped_key);
+    }
+    hasher.finalize().into()
+}
+
+fn xor(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (out, (a, b)) in out.iter_mut().zip(a.iter().zip(b)) {
+        *out = a ^ b;
+    }
+    out
+}
+
+/// Encrypts the payload once and wraps its key for every recipient.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::envelope::seal;
+/// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 64, 16);
+/// let (user, user_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let (auditor, auditor_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let envelope = seal(&[&user, &auditor], b"for both of us", &mut rng).unwrap();
+/// assert_eq!(Ok(b"for both of us".to_vec()), envelope.open(&user_key));
+/// assert_eq!(Ok(b"for both of us".to_vec()), envelope.open(&auditor_key));
+/// ```
+pub fn seal<R: RngCore>(
+    recipients: &[&PublicKey<BigInt>],
+    payload: &[u8],
+    rng: &mut R,
+) -> EnvelopeResult<Envelope> {
+    if recipients.is_empty() {
+        return Err(EnvelopeError::NoRecipients);
+    }
+    let mut payload_key = [0u8; 32];
+    rng.fill_bytes(&mut payload_key);
+    let slots: Vec<Slot> = recipients
+        .iter()
+        .map(|key| {
+            let recipient = recipient_id(key);
+            let group = &key.group;
+            let y = utils::gen_bigint_range(rng, &BigInt::from(1), &(&group.q - 1u32));
+            let c = group.g.modpow(&y, &group.p);
+            let shared = key.h.modpow(&y, &group.p);
+            let wrapped_key = xor(&payload_key, &wrapping_key(&recipient, &c, &shared));
+            Slot {
+                recipient,
+                c,
+                wrapped_key,
+            }
+        })
+        .collect();
+    // the payload key is fresh for every envelope, so a fixed nonce is never reused
+    let aad = slots_digest(&slots);
+    let payload = ChaCha20Poly1305::new(Key::from_slice(&payload_key))
+        .encrypt(
+            &Nonce::default(),
+            Payload {
+                msg: payload,
+                aad: &aad,
+            },
+        )
+        .expect("payload fits in a ChaCha20-Poly1305 message");
+    Ok(Envelope { slots, payload })
+}
+
+impl Envelope {
+    /// Finds the slot of the key, unwraps the payload key and decrypts the payload.
+    pub fn open(&self, key: &PrivateKey<BigInt>) -> EnvelopeResult<Vec<u8>> {
+        let pubkey = key.group.public_key(key.group.g.modpow(&key.x, &key.group.p));
+        let recipient = recipient_id(&pubkey);
+        let slot = self
+            .slots
+            .iter()
+            .find(|slot| slot.recipient == recipient)
+            .ok_or(EnvelopeError::NoSlot)?;
+        // c = 1 would make the shared secret 1 for every key
+        if !in_group(&slot.c, &key.group.p) || slot.c == BigInt::from(1) {
+            return Err(EnvelopeError::InvalidCiphertext);
+        }
+        let shared = slot.c.modpow(&key.x, &key.group.p);
+        let payload_key = xor(
+            &slot.wrapped_key,
+            &wrapping_key(&recipient, &slot.c, &shared),
+        );
+        let aad = slots_digest(&self.slots);
+        ChaCha20Poly1305::new(Key::from_slice(&payload_key))
+            .decrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &self.payload,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EnvelopeError::InvalidCiphertext)
+    }
+
+    /// Ids of the recipients, in slot order.
+    pub fn recipients(&self) -> impl Iterator<Item = &RecipientId> {
+        self.slots.iter().map(|slot| &slot.recipient)
+    }
+
+    /// SCALE encoding of the envelope.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode()
+    }
+
+    /// Decodes the SCALE encoding of an envelope, the input must be consumed entirely.
+    pub fn from_bytes(bytes: &[u8]) -> EnvelopeResult<Self> {
+        let mut input = bytes;
+        let envelope =
+            Envelope::decode(&mut input).map_err(|_| EnvelopeError::InvalidCiphertext)?;
+        if !input.is_empty() {
+            return Err(EnvelopeError::InvalidCiphertext);
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::StandardGroup;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_recipients_on_different_groups() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let (user, user_key) = StandardGroup::Modp1536.generate_key_pair(&mut rng);
+        let (group_key, _) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (auditor, auditor_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let (_, stranger_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+
+        let payload = vec![0x5au8; 1000];
+        let envelope = seal(&[&user, &auditor], &payload, &mut rng).unwrap();
+        assert_eq!(payload.len() + 16, envelope.payload.len());
+        assert_eq!(
+            vec![&recipient_id(&user), &recipient_id(&auditor)],
+            envelope.recipients().collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(payload.clone()), envelope.open(&user_key));
+        assert_eq!(Ok(payload), envelope.open(&auditor_key));
+        assert_eq!(Err(EnvelopeError::NoSlot), envelope.open(&stranger_key));
+        assert_eq!(
+            Err(EnvelopeError::NoRecipients),
+            seal(&[], b"nobody", &mut rng)
+        );
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let (group_key, _) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (user, user_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let (auditor, _) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let envelope = seal(&[&user, &auditor], b"audited", &mut rng).unwrap();
+
+        let mut changed = envelope.clone();
+        changed.payload[0] ^= 1;
+        assert_eq!(
+            Err(EnvelopeError::InvalidCiphertext),
+            changed.open(&user_key)
+        );
+        // a change to another recipient's slot is caught as well
+        let mut changed = envelope.clone();
+        changed.slots[1].wrapped_key[0] ^= 1;
+        assert_eq!(
+            Err(EnvelopeError::InvalidCiphertext),
+            changed.open(&user_key)
+        );
+        let bytes = envelope.to_bytes();
+        assert_eq!(Ok(envelope.clone()), Envelope::from_bytes(&bytes));
+        assert_eq!(
+            Err(EnvelopeError::InvalidCiphertext),
+            Envelope::from_bytes(&bytes[..bytes.len() - 1])
+        );
+        for c in [0u32, 1u32] {
+            let mut changed = envelope.clone();
+            changed.slots[0].c = BigInt::from(c);
+            assert_eq!(
+                Err(EnvelopeError::InvalidCiphertext),
+                changed.open(&user_key)
+            );
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::groups::GroupParams;
+use alloc::sync::Arc;
 use rand_core::RngCore;
 
 /// Error for key generation.
@@ -8,31 +10,35 @@ pub struct GenError;
 #[allow(unused)]
 pub type GenResult<T> = std::result::Result<T, GenError>;
 
+/// Error for parsing keys and ciphertexts from strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError;
+
 /// init private key structure for elgamal encryption.
+/// p, g and bit_length live in the group, which is shared by every key on it.
 #[derive(Debug, Clone)]
 pub struct PrivateKey<I> {
-    pub p: I,
-    pub g: I,
+    pub group: Arc<GroupParams<I>>,
     pub x: I,
-    pub bit_length: u32,
 }
 
 /// Init public key structure for elgamal encryption.
+/// p, g and bit_length live in the group, which is shared by every key on it.
 #[derive(Debug, Clone)]
 pub struct PublicKey<I> {
-    pub p: I,
-    pub g: I,
+    pub group: Arc<GroupParams<I>>,
     pub h: I,
-    pub bit_length: u32,
 }
 
 /// A trait to use a RNG and elgamal key to encrypt plaintext to UTF_16LE string.
-pub trait Encryption<I> {
-    fn encrypt<R: RngCore>(&self, key: &PublicKey<I>, rng: &mut R) -> String;
+/// K is the key type, `PublicKey` for plain elgamal, schemes with other keys set their own.
+/// Returns None if the key cannot encrypt messages, see `GroupParams::encodes_messages`.
+pub trait Encryption<I, K = PublicKey<I>> {
+    fn encrypt<R: RngCore>(&self, key: &K, rng: &mut R) -> Option<String>;
 }
 
 /// A trait to use private key to decrypt to plaintext
-pub trait  Decryption<I>{
-    fn decrypt(&self,key: &PrivateKey<I>) -> Option<String>;
+pub trait  Decryption<I, K = PrivateKey<I>>{
+    fn decrypt(&self,key: &K) -> Option<String>;
 }
 
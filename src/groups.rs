@@ -0,0 +1,686 @@
+//! groups mod
+//! well-known safe prime groups from RFC 3526 (MODP) and RFC 7919 (FFDHE).
+//! every prime is p = 2q + 1 with q prime and generator g = 2, which generates
+//! the subgroup of quadratic residues of order q.
+//! `GroupParams` carries the group separately from key material. keys hold it
+//! behind an `Arc`, so many keys share one validated group. `RawGroupPublicKey`
+//! refers to a group by its id, resolved through a `GroupRegistry`, while
+//! `RawPublicKey` keeps its inline p, g and bit_length.
+use crate::elgamal::{chunk_size, generate_key_pair};
+use crate::generic::{PrivateKey, PublicKey};
+use crate::utils;
+use codec::{Decode, Encode, Error, Input, Output};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::One;
+use rand_core::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Identifier of a group, SHA-256 of its SCALE encoding.
+pub type GroupId = [u8; 32];
+
+/// Error for group validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupError {
+    /// p does not have bit_length bits.
+    BitLength,
+    /// p or q = (p - 1) / 2 is not prime.
+    NotSafePrime,
+    /// g is not an element of order q or 2q.
+    InvalidGenerator,
+}
+
+/// Type alias for group results.
+pub type GroupResult<T> = std::result::Result<T, GroupError>;
+
+/// Parameters of a safe prime group p = 2q + 1 with generator g.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupParams<I = BigInt> {
+    pub p: I,
+    pub q: I,
+    pub g: I,
+    pub bit_length: u32,
+}
+
+impl GroupParams {
+    /// Group of the safe prime p with generator g, q is derived from p.
+    pub fn new(p: BigInt, g: BigInt, bit_length: u32) -> Self {
+        let q = (&p - 1u32) / 2u32;
+        GroupParams {
+            p,
+            q,
+            g,
+            bit_length,
+        }
+    }
+
+    /// Identifier of the group, suitable for storing instead of p and g.
+    /// # Logic Desc
+    /// ```text
+    /// id = SHA-256("elgamal_wasm/group" || SCALE(p, g, bit_length))
+    /// ```
+    pub fn id(&self) -> GroupId {
+        let mut hasher = Sha256::new();
+        hasher.update(b"elgamal_wasm/group");
+        hasher.update(self.encode());
+        hasher.finalize().into()
+    }
+
+    /// Checks that p is a safe prime of bit_length bits and g generates a large subgroup.
+    /// Solovay-Strassen is run with i_confidence rounds on both p and q, seeded from the group id.
+    pub fn validate(&self, i_confidence: u32) -> GroupResult<()> {
+        if self.p.bits() != self.bit_length as u64 {
+            return Err(GroupError::BitLength);
+        }
+        let seed: Vec<u32> = self
+            .id()
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&seed);
+        let two = BigInt::from(2);
+        if self.q != (&self.p - 1u32) / 2u32
+            || self.q <= two
+            || !utils::solovay_strassen(&self.p, i_confidence, &mut rng)
+            || !utils::solovay_strassen(&self.q, i_confidence, &mut rng)
+        {
+            return Err(GroupError::NotSafePrime);
+        }
+        // with p = 2q + 1 every element other than 1 and p - 1 has order q or 2q
+        if self.g <= BigInt::one() || self.g >= &self.p - 1u32 {
+            return Err(GroupError::InvalidGenerator);
+        }
+        Ok(())
+    }
+
+    /// Whether messages can be encrypted on the group: chunks of `chunk_size(bit_length)`
+    /// bytes plus one must stay below q, and p = 3 mod 4 so that -1 is a non-residue.
+    /// Keys read from strings, DER or JSON may carry a bit_length too large for p.
+    pub fn encodes_messages(&self) -> bool {
+        let k = chunk_size(self.bit_length) as u64;
+        k > 0 && k * 8 < self.q.bits() && self.p.mod_floor(&BigInt::from(4)) == BigInt::from(3)
+    }
+
+    /// Public key h on the group.
+    pub fn public_key(self: &Arc<Self>, h: BigInt) -> PublicKey<BigInt> {
+        PublicKey {
+            group: self.clone(),
+            h,
+        }
+    }
+
+    /// Private key x on the group.
+    pub fn private_key(self: &Arc<Self>, x: BigInt) -> PrivateKey<BigInt> {
+        PrivateKey {
+            group: self.clone(),
+            x,
+        }
+    }
+
+    /// Generates a key pair on the group.
+    pub fn generate_key_pair<R: RngCore>(
+        self: &Arc<Self>,
+        rng: &mut R,
+    ) -> (PublicKey<BigInt>, PrivateKey<BigInt>) {
+        generate_key_pair(self, rng)
+    }
+}
+
+/// SCALE encodes a non-negative integer as its little endian byte vector.
+pub(crate) fn encode_bigint<T: Output + ?Sized>(value: &BigInt, dest: &mut T) {
+    value.to_bytes_le().1.encode_to(dest);
+}
+
+pub(crate) fn decode_bigint<I: Input>(input: &mut I) -> Result<BigInt, Error> {
+    let bytes = Vec::<u8>::decode(input)?;
+    Ok(BigInt::from_bytes_le(Sign::Plus, &bytes))
+}
+
+/// SCALE encodes p, g and bit_length, p and g as little endian byte vectors.
+/// q is derived from p and is not encoded.
+impl Encode for GroupParams {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        encode_bigint(&self.p, dest);
+        encode_bigint(&self.g, dest);
+        self.bit_length.encode_to(dest);
+    }
+}
+
+impl Decode for GroupParams {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let p = decode_bigint(input)?;
+        let g = decode_bigint(input)?;
+        let bit_length = u32::decode(input)?;
+        Ok(GroupParams::new(p, g, bit_length))
+    }
+}
+
+/// The raw public key referencing its group by id, h as little endian bytes.
+/// Unlike `RawPublicKey` it needs the group in a `GroupRegistry` to be restored.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct RawGroupPublicKey {
+    pub group: GroupId,
+    pub h: Vec<u8>,
+}
+
+impl RawGroupPublicKey {
+    /// Raw form of a public key, referencing the key's group by id.
+    pub fn new(key: &PublicKey<BigInt>) -> Self {
+        RawGroupPublicKey {
+            group: key.group.id(),
+            h: key.h.to_bytes_le().1,
+        }
+    }
+}
+
+/// Groups by id, so each group is checked once and then shared by its keys.
+#[derive(Debug, Clone, Default)]
+pub struct GroupRegistry {
+    groups: HashMap<GroupId, Arc<GroupParams>>,
+}
+
+impl GroupRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding every standard group, which are trusted without validation.
+    pub fn with_standard_groups() -> Self {
+        let mut registry = Self::new();
+        for group in StandardGroup::ALL {
+            registry.insert(Arc::new(group.params()));
+        }
+        registry
+    }
+
+    /// Validates a group and caches it, groups already registered are not checked again.
+    pub fn register(&mut self, group: GroupParams, i_confidence: u32) -> GroupResult<GroupId> {
+        let id = group.id();
+        if let Entry::Vacant(entry) = self.groups.entry(id) {
+            group.validate(i_confidence)?;
+            entry.insert(Arc::new(group));
+        }
+        Ok(id)
+    }
+
+    /// Adds a trusted group without validation, e.g. the group of a key generated locally.
+    pub fn insert(&mut self, group: Arc<GroupParams>) -> GroupId {
+        let id = group.id();
+        self.groups.entry(id).or_insert(group);
+        id
+    }
+
+    /// The group with the given id.
+    pub fn get(&self, id: &GroupId) -> Option<&Arc<GroupParams>> {
+        self.groups.get(id)
+    }
+
+    /// Number of registered groups.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Whether no group is registered.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Restores a public key from its raw form, if its group is registered.
+    pub fn public_key(&self, raw_key: &RawGroupPublicKey) -> Option<PublicKey<BigInt>> {
+        let group = self.get(&raw_key.group)?;
+        Some(group.public_key(BigInt::from_bytes_le(Sign::Plus, &raw_key.h)))
+    }
+}
+
+/// A standard group with vetted parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardGroup {
+    /// RFC 3526 MODP group, 1536 bits.
+    Modp1536,
+    /// RFC 3526 MODP group, 2048 bits.
+    Modp2048,
+    /// RFC 3526 MODP group, 3072 bits.
+    Modp3072,
+    /// RFC 3526 MODP group, 4096 bits.
+    Modp4096,
+    /// RFC 3526 MODP group, 6144 bits.
+    Modp6144,
+    /// RFC 3526 MODP group, 8192 bits.
+    Modp8192,
+    /// RFC 7919 FFDHE group, 2048 bits.
+    Ffdhe2048,
+    /// RFC 7919 FFDHE group, 3072 bits.
+    Ffdhe3072,
+    /// RFC 7919 FFDHE group, 4096 bits.
+    Ffdhe4096,
+    /// RFC 7919 FFDHE group, 6144 bits.
+    Ffdhe6144,
+    /// RFC 7919 FFDHE group, 8192 bits.
+    Ffdhe8192,
+}
+
+impl StandardGroup {
+    /// Every standard group, MODP groups first.
+    pub const ALL: [StandardGroup; 11] = [
+        StandardGroup::Modp1536,
+        StandardGroup::Modp2048,
+        StandardGroup::Modp3072,
+        StandardGroup::Modp4096,
+        StandardGroup::Modp6144,
+        StandardGroup::Modp8192,
+        StandardGroup::Ffdhe2048,
+        StandardGroup::Ffdhe3072,
+        StandardGroup::Ffdhe4096,
+        StandardGroup::Ffdhe6144,
+        StandardGroup::Ffdhe8192,
+    ];
+
+    /// Name of the group, e.g. "modp2048" or "ffdhe2048".
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandardGroup::Modp1536 => "modp1536",
+            StandardGroup::Modp2048 => "modp2048",
+            StandardGroup::Modp3072 => "modp3072",
+            StandardGroup::Modp4096 => "modp4096",
+            StandardGroup::Modp6144 => "modp6144",
+            StandardGroup::Modp8192 => "modp8192",
+            StandardGroup::Ffdhe2048 => "ffdhe2048",
+            StandardGroup::Ffdhe3072 => "ffdhe3072",
+            StandardGroup::Ffdhe4096 => "ffdhe4096",
+            StandardGroup::Ffdhe6144 => "ffdhe6144",
+            StandardGroup::Ffdhe8192 => "ffdhe8192",
+        }
+    }
+
+    /// Look up a group by its name.
+    pub fn from_name(name: &str) -> Option<StandardGroup> {
+        StandardGroup::ALL
+            .into_iter()
+            .find(|group| group.name() == name)
+    }
+
+    /// Bit length of the prime p.
+    pub fn bit_length(&self) -> u32 {
+        match self {
+            StandardGroup::Modp1536 => 1536,
+            StandardGroup::Modp2048 => 2048,
+            StandardGroup::Modp3072 => 3072,
+            StandardGroup::Modp4096 => 4096,
+            StandardGroup::Modp6144 => 6144,
+            StandardGroup::Modp8192 => 8192,
+            StandardGroup::Ffdhe2048 => 2048,
+            StandardGroup::Ffdhe3072 => 3072,
+            StandardGroup::Ffdhe4096 => 4096,
+            StandardGroup::Ffdhe6144 => 6144,
+            StandardGroup::Ffdhe8192 => 8192,
+        }
+    }
+
+    /// The safe prime p.
+    pub fn p(&self) -> BigInt {
+        let hex = match self {
+            StandardGroup::Modp1536 => MODP_1536_P,
+            StandardGroup::Modp2048 => MODP_2048_P,
+            StandardGroup::Modp3072 => MODP_3072_P,
+            StandardGroup::Modp4096 => MODP_4096_P,
+            StandardGroup::Modp6144 => MODP_6144_P,
+            StandardGroup::Modp8192 => MODP_8192_P,
+            StandardGroup::Ffdhe2048 => FFDHE_2048_P,
+            StandardGroup::Ffdhe3072 => FFDHE_3072_P,
+            StandardGroup::Ffdhe4096 => FFDHE_4096_P,
+            StandardGroup::Ffdhe6144 => FFDHE_6144_P,
+            StandardGroup::Ffdhe8192 => FFDHE_8192_P,
+        };
+        BigInt::from(BigUint::parse_bytes(hex.as_bytes(), 16).unwrap())
+    }
+
+    /// The generator g = 2.
+    pub fn g(&self) -> BigInt {
+        BigInt::from(2)
+    }
+
+    /// The prime order q = (p - 1) / 2 of the subgroup generated by g.
+    pub fn q(&self) -> BigInt {
+        (self.p() - 1u32) / 2u32
+    }
+
+    /// Parameters of the group.
+    pub fn params(&self) -> GroupParams {
+        GroupParams::new(self.p(), self.g(), self.bit_length())
+    }
+
+    /// Generates a key pair on a new shared instance of the group.
+    /// Use `GroupParams::generate_key_pair` on one `Arc` to share it between many keys.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::generic::{Decryption, Encryption};
+    /// use elgamal::groups::StandardGroup;
+    /// let mut rng = mt19937::MT19937::new_with_slice_seed(&[2929]);
+    /// let (pubkey, private_key) = StandardGroup::Modp1536.generate_key_pair(&mut rng);
+    /// let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+    /// assert_eq!(Some(String::from("message")), cipher.decrypt(&private_key));
+    /// ```
+    pub fn generate_key_pair<R: RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> (PublicKey<BigInt>, PrivateKey<BigInt>) {
+        generate_key_pair(&Arc::new(self.params()), rng)
+    }
+}
+
+/// RFC 3526 1536-bit MODP prime.
+const MODP_1536_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA237327FFFFFFFFFFFFFFFF";
+
+/// RFC 3526 2048-bit MODP prime.
+const MODP_2048_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF";
+
+/// RFC 3526 3072-bit MODP prime.
+const MODP_3072_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+    A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+    D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+    08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF";
+
+/// RFC 3526 4096-bit MODP prime.
+const MODP_4096_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+    A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+    D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+    08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+    88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+    DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+    233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+    93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C934063199FFFFFFFFFFFFFFFF";
+
+/// RFC 3526 6144-bit MODP prime.
+const MODP_6144_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+    A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+    D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+    08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+    88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+    DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+    233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+    93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026\
+    C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE\
+    B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B\
+    DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC\
+    F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E\
+    59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA\
+    CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76\
+    F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468\
+    043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DCC4024FFFFFFFFFFFFFFFF";
+
+/// RFC 3526 8192-bit MODP prime.
+const MODP_8192_P: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+    020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+    4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05\
+    98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB\
+    9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B\
+    E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718\
+    3995497CEA956AE515D2261898FA051015728E5A8AAAC42DAD33170D04507A33\
+    A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7\
+    ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864\
+    D87602733EC86A64521F2B18177B200CBBE117577A615D6C770988C0BAD946E2\
+    08E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7\
+    88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8\
+    DBBBC2DB04DE8EF92E8EFC141FBECAA6287C59474E6BC05D99B2964FA090C3A2\
+    233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9\
+    93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026\
+    C1D4DCB2602646DEC9751E763DBA37BDF8FF9406AD9E530EE5DB382F413001AE\
+    B06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B\
+    DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92EC\
+    F032EA15D1721D03F482D7CE6E74FEF6D55E702F46980C82B5A84031900B1C9E\
+    59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA\
+    CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76\
+    F550AA3D8A1FBFF0EB19CCB1A313D55CDA56C9EC2EF29632387FE8D76E3C0468\
+    043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DBE115974A3926F12FEE5E4\
+    38777CB6A932DF8CD8BEC4D073B931BA3BC832B68D9DD300741FA7BF8AFC47ED\
+    2576F6936BA424663AAB639C5AE4F5683423B4742BF1C978238F16CBE39D652D\
+    E3FDB8BEFC848AD922222E04A4037C0713EB57A81A23F0C73473FC646CEA306B\
+    4BCBC8862F8385DDFA9D4B7FA2C087E879683303ED5BDD3A062B3CF5B3A278A6\
+    6D2A13F83F44F82DDF310EE074AB6A364597E899A0255DC164F31CC50846851D\
+    F9AB48195DED7EA1B1D510BD7EE74D73FAF36BC31ECFA268359046F4EB879F92\
+    4009438B481C6CD7889A002ED5EE382BC9190DA6FC026E479558E4475677E9AA\
+    9E3050E2765694DFC81F56E880B96E7160C980DD98EDD3DFFFFFFFFFFFFFFFFF";
+
+/// RFC 7919 2048-bit FFDHE prime.
+const FFDHE_2048_P: &str = "\
+    FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695\
+    A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A\
+    D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935\
+    984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A\
+    BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4\
+    AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61\
+    9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005\
+    C58EF1837D1683B2C6F34A26C1B2EFFA886B423861285C97FFFFFFFFFFFFFFFF";
+
+/// RFC 7919 3072-bit FFDHE prime.
+const FFDHE_3072_P: &str = "\
+    FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695\
+    A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A\
+    D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935\
+    984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A\
+    BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4\
+    AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61\
+    9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005\
+    C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B\
+    BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C\
+    AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF\
+    5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E\
+    0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B66C62E37FFFFFFFFFFFFFFFF";
+
+/// RFC 7919 4096-bit FFDHE prime.
+const FFDHE_4096_P: &str = "\
+    FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695\
+    A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A\
+    D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935\
+    984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A\
+    BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4\
+    AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61\
+    9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005\
+    C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B\
+    BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C\
+    AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF\
+    5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E\
+    0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB\
+    7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A\
+    7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038\
+    092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF\
+    8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E655F6AFFFFFFFFFFFFFFFF";
+
+/// RFC 7919 6144-bit FFDHE prime.
+const FFDHE_6144_P: &str = "\
+    FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695\
+    A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A\
+    D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935\
+    984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A\
+    BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4\
+    AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61\
+    9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005\
+    C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B\
+    BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C\
+    AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF\
+    5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E\
+    0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB\
+    7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A\
+    7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038\
+    092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF\
+    8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A\
+    4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C\
+    B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477\
+    A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E\
+    7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992\
+    EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C\
+    D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117\
+    8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69\
+    62A69526D43161C1A41D570D7938DAD4A40E329CD0E40E65FFFFFFFFFFFFFFFF";
+
+/// RFC 7919 8192-bit FFDHE prime.
+const FFDHE_8192_P: &str = "\
+    FFFFFFFFFFFFFFFFADF85458A2BB4A9AAFDC5620273D3CF1D8B9C583CE2D3695\
+    A9E13641146433FBCC939DCE249B3EF97D2FE363630C75D8F681B202AEC4617A\
+    D3DF1ED5D5FD65612433F51F5F066ED0856365553DED1AF3B557135E7F57C935\
+    984F0C70E0E68B77E2A689DAF3EFE8721DF158A136ADE73530ACCA4F483A797A\
+    BC0AB182B324FB61D108A94BB2C8E3FBB96ADAB760D7F4681D4F42A3DE394DF4\
+    AE56EDE76372BB190B07A7C8EE0A6D709E02FCE1CDF7E2ECC03404CD28342F61\
+    9172FE9CE98583FF8E4F1232EEF28183C3FE3B1B4C6FAD733BB5FCBC2EC22005\
+    C58EF1837D1683B2C6F34A26C1B2EFFA886B4238611FCFDCDE355B3B6519035B\
+    BC34F4DEF99C023861B46FC9D6E6C9077AD91D2691F7F7EE598CB0FAC186D91C\
+    AEFE130985139270B4130C93BC437944F4FD4452E2D74DD364F2E21E71F54BFF\
+    5CAE82AB9C9DF69EE86D2BC522363A0DABC521979B0DEADA1DBF9A42D5C4484E\
+    0ABCD06BFA53DDEF3C1B20EE3FD59D7C25E41D2B669E1EF16E6F52C3164DF4FB\
+    7930E9E4E58857B6AC7D5F42D69F6D187763CF1D5503400487F55BA57E31CC7A\
+    7135C886EFB4318AED6A1E012D9E6832A907600A918130C46DC778F971AD0038\
+    092999A333CB8B7A1A1DB93D7140003C2A4ECEA9F98D0ACC0A8291CDCEC97DCF\
+    8EC9B55A7F88A46B4DB5A851F44182E1C68A007E5E0DD9020BFD64B645036C7A\
+    4E677D2C38532A3A23BA4442CAF53EA63BB454329B7624C8917BDD64B1C0FD4C\
+    B38E8C334C701C3ACDAD0657FCCFEC719B1F5C3E4E46041F388147FB4CFDB477\
+    A52471F7A9A96910B855322EDB6340D8A00EF092350511E30ABEC1FFF9E3A26E\
+    7FB29F8C183023C3587E38DA0077D9B4763E4E4B94B2BBC194C6651E77CAF992\
+    EEAAC0232A281BF6B3A739C1226116820AE8DB5847A67CBEF9C9091B462D538C\
+    D72B03746AE77F5E62292C311562A846505DC82DB854338AE49F5235C95B9117\
+    8CCF2DD5CACEF403EC9D1810C6272B045B3B71F9DC6B80D63FDD4A8E9ADB1E69\
+    62A69526D43161C1A41D570D7938DAD4A40E329CCFF46AAA36AD004CF600C838\
+    1E425A31D951AE64FDB23FCEC9509D43687FEB69EDD1CC5E0B8CC3BDF64B10EF\
+    86B63142A3AB8829555B2F747C932665CB2C0F1CC01BD70229388839D2AF05E4\
+    54504AC78B7582822846C0BA35C35F5C59160CC046FD8251541FC68C9C86B022\
+    BB7099876A460E7451A8A93109703FEE1C217E6C3826E52C51AA691E0E423CFC\
+    99E9E31650C1217B624816CDAD9A95F9D5B8019488D9C0A0A1FE3075A577E231\
+    83F81D4A3F2FA4571EFC8CE0BA8A4FE8B6855DFE72B0A66EDED2FBABFBE58A30\
+    FAFABE1C5D71A87E2F741EF8C1FE86FEA6BBFDE530677F0D97D11D49F7A8443D\
+    0822E506A9F4614E011E2A94838FF88CD68C8BB7C5C6424CFFFFFFFFFFFFFFFF";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_standard_groups_are_safe_prime_groups() {
+        for group in StandardGroup::ALL {
+            let p = group.p();
+            assert_eq!(group.bit_length() as u64, p.bits(), "{}", group.name());
+            assert_eq!(Some(group), StandardGroup::from_name(group.name()));
+            // p = 7 mod 8 makes 2 a quadratic residue, so g generates the order q subgroup
+            assert_eq!(BigInt::from(7), &p % 8u32, "{}", group.name());
+        }
+        // fermat witnesses for p and q, the larger groups are too slow for debug builds
+        for group in [StandardGroup::Modp1536, StandardGroup::Ffdhe2048] {
+            let (p, q) = (group.p(), group.q());
+            let three = BigInt::from(3);
+            assert!(three.modpow(&(&p - 1u32), &p).is_one(), "{}", group.name());
+            assert!(three.modpow(&(&q - 1u32), &q).is_one(), "{}", group.name());
+            assert!(group.g().modpow(&q, &p).is_one(), "{}", group.name());
+        }
+    }
+
+    #[test]
+    fn test_standard_group_known_values() {
+        let tail = |group: StandardGroup| {
+            let hex = group.p().to_str_radix(16);
+            hex[hex.len() - 32..].to_uppercase()
+        };
+        assert_eq!(
+            "15728E5A8AACAA68FFFFFFFFFFFFFFFF",
+            tail(StandardGroup::Modp2048)
+        );
+        assert_eq!(
+            "886B423861285C97FFFFFFFFFFFFFFFF",
+            tail(StandardGroup::Ffdhe2048)
+        );
+    }
+
+    #[test]
+    fn test_group_params_encode_and_validate() {
+        let params = StandardGroup::Modp1536.params();
+        let encoded = params.encode();
+        assert_eq!(params, GroupParams::decode(&mut &encoded[..]).unwrap());
+        assert_eq!(Ok(()), params.validate(4));
+        assert_ne!(params.id(), StandardGroup::Ffdhe2048.params().id());
+
+        let (pubkey, _) = crate::generate_qr_pub_key(&SEED, 32, 16);
+        let group = (*pubkey.group).clone();
+        assert_eq!(Ok(()), group.validate(16));
+        let mut bad = group.clone();
+        bad.g = BigInt::one();
+        assert_eq!(Err(GroupError::InvalidGenerator), bad.validate(16));
+        let not_safe = GroupParams::new(&group.p + 2u32, group.g.clone(), 32);
+        assert_eq!(Err(GroupError::NotSafePrime), not_safe.validate(16));
+        let short = GroupParams::new(group.p.clone(), group.g.clone(), 64);
+        assert_eq!(Err(GroupError::BitLength), short.validate(16));
+    }
+
+    #[test]
+    fn test_registry_shares_groups_between_keys() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let (group_key, _) = crate::generate_qr_pub_key(&SEED, 32, 16);
+        let mut registry = GroupRegistry::with_standard_groups();
+        let id = registry.register((*group_key.group).clone(), 16).unwrap();
+        assert_eq!(StandardGroup::ALL.len() + 1, registry.len());
+
+        let group = registry.get(&id).unwrap().clone();
+        for _ in 0..3 {
+            let (pubkey, _) = group.generate_key_pair(&mut rng);
+            // keys on the group share one instance of it
+            assert!(Arc::ptr_eq(&group, &pubkey.group));
+            let raw_key = RawGroupPublicKey::new(&pubkey);
+            assert_eq!(id, raw_key.group);
+            let decoded = RawGroupPublicKey::decode(&mut &raw_key.encode()[..]).unwrap();
+            let restored = registry.public_key(&decoded).unwrap();
+            assert!(Arc::ptr_eq(&group, &restored.group));
+            assert_eq!(format!("{}", pubkey), format!("{}", restored));
+        }
+        let (pubkey, _) = StandardGroup::Ffdhe2048.generate_key_pair(&mut rng);
+        let raw_key = RawGroupPublicKey::new(&pubkey);
+        assert!(registry.public_key(&raw_key).is_some());
+        assert!(GroupRegistry::new().public_key(&raw_key).is_none());
+    }
+}
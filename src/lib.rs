@@ -2,24 +2,86 @@
 //! elgamal_wasm
 //! This is a third part for elgamal security algorithm
 //! used for generating public keys for elgamal systems and etc.
+extern crate alloc;
+
 mod elgamal;
 pub use crate::elgamal::*;
+pub mod batch;
+pub mod cramer_shoup;
+pub mod der;
+#[cfg(any(feature = "ristretto", feature = "secp256k1"))]
+pub mod ec;
+pub mod envelope;
 pub mod generic;
+pub mod groups;
+pub mod precompute;
+pub mod proofs;
+pub mod proxy;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod shuffle;
+pub mod signature;
+pub mod stream;
+pub mod threshold;
+pub mod timelock;
 pub mod utils;
 
-use crate::generic::PublicKey;
+use crate::generic::{ParseError, PrivateKey, PublicKey};
+use crate::groups::GroupParams;
 use num_bigint::{BigInt, BigUint};
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
 impl fmt::Display for PublicKey<BigInt> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {}, {})", self.p, self.g, self.h)
+        write!(f, "({}, {}, {})", self.group.p, self.group.g, self.h)
     }
 }
 
 /// trait for printing some struct
-pub trait KeyFormat {
-    fn from_hex_str(key_str: &str) -> Self;
+/// keys are printed as "0xp, 0xg, 0xk, bit_length" where k is h or x.
+/// parsing tolerates surrounding whitespace, upper case digits and a missing "0x".
+pub trait KeyFormat: Sized {
+    /// Parses a key string, panics if it is malformed.
+    fn from_hex_str(key_str: &str) -> Self {
+        Self::try_from_hex_str(key_str).expect("malformed key string")
+    }
+    /// Parses a key string, None if it is malformed.
+    fn try_from_hex_str(key_str: &str) -> Option<Self>;
+    /// Prints the key string parsed by `from_hex_str`.
+    fn to_hex_str(&self) -> String;
+}
+
+/// Splits "0xa, 0xb, 0xc, bit_length" into its three integers and bit length.
+fn parse_hex_fields(key_str: &str) -> Option<(BigInt, BigInt, BigInt, u32)> {
+    let fields: Vec<&str> = key_str.split(',').map(str::trim).collect();
+    let parse_hex = |field: &str| {
+        let digits = field
+            .strip_prefix("0x")
+            .or_else(|| field.strip_prefix("0X"))
+            .unwrap_or(field);
+        BigUint::parse_bytes(digits.as_bytes(), 16).map(BigInt::from)
+    };
+    match fields.as_slice() {
+        [a, b, c, bit_length] => Some((
+            parse_hex(a)?,
+            parse_hex(b)?,
+            parse_hex(c)?,
+            bit_length.parse::<u32>().ok()?,
+        )),
+        _ => None,
+    }
+}
+
+fn format_hex_fields(a: &BigInt, b: &BigInt, c: &BigInt, bit_length: u32) -> String {
+    format!(
+        "0x{}, 0x{}, 0x{}, {}",
+        a.to_str_radix(16),
+        b.to_str_radix(16),
+        c.to_str_radix(16),
+        bit_length
+    )
 }
 
 impl KeyFormat for PublicKey<BigInt> {
@@ -30,23 +92,43 @@ impl KeyFormat for PublicKey<BigInt> {
     /// use elgamal_wasm::KeyFormat;
     /// use num_bigint::BigInt;
     /// let pub_key:PublicKey<BigInt> = PublicKey::from_hex_str("0x747c85d7, 0x747c85d6, 0xb2040843, 32");
+    /// assert_eq!("0x747c85d7, 0x747c85d6, 0xb2040843, 32", pub_key.to_hex_str());
     /// ~~~
     #[inline]
-    fn from_hex_str(key_str: &str) -> PublicKey<BigInt> {
-        let keys: Vec<_> = key_str.split(", ").collect();
-        let p =
-            BigInt::from(BigUint::parse_bytes(keys[0].replace("0x", "").as_bytes(), 16).unwrap());
-        let g =
-            BigInt::from(BigUint::parse_bytes(keys[1].replace("0x", "").as_bytes(), 16).unwrap());
-        let h =
-            BigInt::from(BigUint::parse_bytes(keys[2].replace("0x", "").as_bytes(), 16).unwrap());
-        let bit_length = keys[3].parse::<u32>().unwrap();
-        PublicKey {
-            p,
-            g,
-            h,
-            bit_length,
-        }
+    fn try_from_hex_str(key_str: &str) -> Option<PublicKey<BigInt>> {
+        let (p, g, h, bit_length) = parse_hex_fields(key_str)?;
+        Some(Arc::new(GroupParams::new(p, g, bit_length)).public_key(h))
+    }
+
+    fn to_hex_str(&self) -> String {
+        format_hex_fields(&self.group.p, &self.group.g, &self.h, self.group.bit_length)
+    }
+}
+
+impl KeyFormat for PrivateKey<BigInt> {
+    #[inline]
+    fn try_from_hex_str(key_str: &str) -> Option<PrivateKey<BigInt>> {
+        let (p, g, x, bit_length) = parse_hex_fields(key_str)?;
+        Some(Arc::new(GroupParams::new(p, g, bit_length)).private_key(x))
+    }
+
+    fn to_hex_str(&self) -> String {
+        format_hex_fields(&self.group.p, &self.group.g, &self.x, self.group.bit_length)
+    }
+}
+
+/// Prints the key string of `KeyFormat`.
+impl fmt::Display for PrivateKey<BigInt> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex_str())
+    }
+}
+
+impl FromStr for PrivateKey<BigInt> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_hex_str(s).ok_or(ParseError)
     }
 }
 
@@ -55,6 +137,41 @@ pub trait KeyGenerator {
     const CONFIDENCE: u32;
     /// Use current data slices as seed and generate a new public key.
     fn yield_pubkey(&self, bit_length: u32) -> Self;
+    /// Mix external entropy into the seed with a versioned derivation and generate a new public key.
+    /// Panics if the new key does not fit `Self`, e.g. a `RawPublicKey` over 256 bits,
+    /// as `yield_pubkey` does.
+    fn yield_pubkey_with_entropy(
+        &self,
+        bit_length: u32,
+        version: SeedVersion,
+        entropy: &[u8],
+    ) -> Self
+    where
+        Self: VersionedSeed + TryFrom<PublicKey<BigInt>>,
+        <Self as TryFrom<PublicKey<BigInt>>>::Error: fmt::Debug,
+    {
+        let seed = self.derive_seed(version, entropy);
+        let new_key = elgamal::generate_pub_key(&seed, bit_length, Self::CONFIDENCE).0;
+        Self::try_from(new_key).expect("new key must fit the key type")
+    }
+}
+
+/// Error for public keys with p, g or h over the 256 bits of `RawPublicKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawKeyError;
+
+impl TryFrom<PublicKey<BigInt>> for RawPublicKey {
+    type Error = RawKeyError;
+
+    fn try_from(key: PublicKey<BigInt>) -> Result<Self, Self::Error> {
+        if [&key.group.p, &key.group.g, &key.h]
+            .iter()
+            .any(|value| value.bits() > 256)
+        {
+            return Err(RawKeyError);
+        }
+        Ok(key.to_raw())
+    }
 }
 
 impl KeyGenerator for RawPublicKey {
@@ -79,12 +196,13 @@ impl KeyGenerator for PublicKey<BigInt> {
 #[cfg(test)]
 mod tests {
     use crate::elgamal::*;
-    use crate::generic::{Encryption, PublicKey};
+    use crate::generic::{Decryption, Encryption, PrivateKey, PublicKey};
     use crate::*;
     use crate::{KeyGenerator, RawKey, RawPublicKey};
     use codec::{Decode, Encode};
     use num_bigint::BigInt;
     use num_traits::Num;
+    use proptest::prelude::*;
     use rand_core::RngCore;
 
     const SEED: [u32; 8] = [
@@ -100,6 +218,20 @@ mod tests {
         let decoded = RawPublicKey::decode(&mut encoded.as_slice()).unwrap();
         let new_key = PublicKey::<BigInt>::from_raw(decoded);
         assert_eq!(format!("{}", pubkey), format!("{}", new_key));
+        // the inline layout: p, g and h as 32 little endian bytes each, then bit_length
+        let word = |value: u32| {
+            let mut bytes = value.to_le_bytes().to_vec();
+            bytes.resize(32, 0);
+            bytes
+        };
+        let layout = [
+            word(0x747c85d7),
+            word(0x747c85d6),
+            word(0xb2040843),
+            32u32.to_le_bytes().to_vec(),
+        ]
+        .concat();
+        assert_eq!(layout, encoded);
     }
 
     #[test]
@@ -131,21 +263,257 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_public_key_generation_v2() {
+        let (pubkey, _) = generate_pub_key_v2(&SEED, 20, 32);
+        assert_eq!("(574439, 388887, 98281)".to_owned(), format!("{}", pubkey));
+        // the legacy generator draws g and h from the stream that found p
+        assert_ne!(
+            format!("{}", generate_pub_key(&SEED, 20, 32).0),
+            format!("{}", pubkey)
+        );
+        let (pubkey_64, mut rng) = generate_pub_key_v2(&SEED, 64, 16);
+        let (again, mut rng_again) = generate_pub_key_v2(&SEED, 64, 16);
+        assert_eq!(format!("{}", pubkey_64), format!("{}", again));
+        assert_eq!(rng.next_u32(), rng_again.next_u32());
+        let q: BigInt = (&pubkey_64.group.p - 1) / 2;
+        assert_ne!(
+            BigInt::from(1),
+            pubkey_64.group.g.modpow(&q, &pubkey_64.group.p)
+        );
+        assert_ne!(
+            BigInt::from(1),
+            pubkey_64
+                .group
+                .g
+                .modpow(&BigInt::from(2), &pubkey_64.group.p)
+        );
+    }
+
+    #[test]
+    fn test_jacobi_matches_euler_criterion() {
+        let p = groups::StandardGroup::Modp1536.p();
+        let exp: BigInt = (&p - 1) / 2;
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        for _ in 0..8 {
+            let a = utils::gen_bigint_range(&mut rng, &BigInt::from(2), &(&p - 1));
+            let euler = a.modpow(&exp, &p);
+            let expected = if euler == BigInt::from(1) { 1 } else { -1 };
+            assert_eq!(BigInt::from(expected), utils::jacobi(&a, &p));
+        }
+    }
+
+    #[test]
+    fn test_malformed_key_str_is_rejected() {
+        for key_str in [
+            "",
+            "0x747c85d7, 0x747c85d6, 0xb2040843",
+            "0x747c85d7, 0x747c85d6, 0xb2040843, 32, 1",
+            "0x747c85d7, 0xzz, 0xb2040843, 32",
+            "0x747c85d7, 0x747c85d6, 0xb2040843, -32",
+        ] {
+            assert!(PublicKey::<BigInt>::try_from_hex_str(key_str).is_none());
+            assert!(key_str.parse::<PrivateKey<BigInt>>().is_err());
+        }
+        assert!("1 2 3".parse::<Ciphertext>().is_err());
+    }
+
+    #[test]
+    fn test_discrete_log() {
+        let (pubkey, mut rng) = generate_pub_key(&SEED, 32, 16);
+        let order: BigInt = &pubkey.group.p - 1;
+        for _ in 0..4 {
+            let x = utils::gen_bigint_range(&mut rng, &BigInt::from(0), &(&order - 1));
+            let h = pubkey.group.g.modpow(&x, &pubkey.group.p);
+            assert_eq!(
+                Some(x),
+                utils::discrete_log(&pubkey.group.g, &h, &pubkey.group.p, &order)
+            );
+        }
+        // 1 is not a power of g in the order q subgroup generated by g^2
+        let g2 = pubkey.group.g.modpow(&BigInt::from(2), &pubkey.group.p);
+        let q: BigInt = &order / 2;
+        assert_eq!(
+            None,
+            utils::discrete_log(&g2, &pubkey.group.g, &pubkey.group.p, &q)
+        );
+    }
+
     #[test]
     fn test_encrypt() {
         let pubkey = PublicKey::<BigInt>::from_hex_str("0x747c85d7, 0x747c85d6, 0xb2040843, 32");
         let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(&SEED);
         let msg = String::from("message");
-        let result = msg.encrypt(&pubkey, &mut rng);
+        let result = msg.encrypt(&pubkey, &mut rng).unwrap();
+        // the chunk encoding changed, see "Ciphertext encoding" in the README: chunks of
+        // (bit_length - 2) / 8 bytes mapped into the QR subgroup, with a padding marker.
+        // earlier releases gave "1954317782 623638905 1 1335914958 1954317782 1931457661 1 1068518055 ",
+        // those ciphertexts do not decrypt with this encoding.
         assert_eq!(
-            "1954317782 623638905 1 1335914958 1954317782 1931457661 1 1068518055 ", result,
+            "1954317782 1942406002 1 1275570525 1954317782 616545444 1 680174467 1 552739052 1 1643547510 ",
+            result,
             "Encrypt result is not correct!"
         );
     }
 
     #[test]
-    fn test_decrypt(){
-        //wait to find a private key and public key to test.
-        todo!();
+    fn test_decrypt() {
+        let (pubkey, mut rng) = generate_pub_key(&SEED, 32, 32);
+        let x = BigInt::from(123456789u32);
+        let group = pubkey.group;
+        let private_key = group.private_key(x.clone());
+        let pubkey = group.public_key(group.g.modpow(&x, &group.p));
+        let msg = String::from("message\0 with trailing zero bytes\0");
+        let cipher = msg.encrypt(&pubkey, &mut rng).unwrap();
+        assert_eq!(Some(msg), cipher.decrypt(&private_key));
+        assert_eq!(None, String::from("").decrypt(&private_key));
+        assert_eq!(None, String::from("1 2 3").decrypt(&private_key));
+    }
+
+    #[test]
+    fn test_decode_utf16_rejects_bad_padding() {
+        // the last chunk must carry the 0x80 end marker
+        assert_eq!(None, decode_utf16(&[BigInt::from(0xfeffu32)], 32));
+        // an integer wider than (bit_length - 2) / 8 bytes
+        assert_eq!(None, decode_utf16(&[BigInt::from(1u64 << 40)], 32));
+    }
+
+    #[test]
+    fn test_encoded_chunks_are_quadratic_residues() {
+        let (pubkey, _) = generate_pub_key(&SEED, 32, 32);
+        let q: BigInt = (&pubkey.group.p - 1) / 2;
+        // all 0xff bytes is the largest possible chunk
+        let msg = "\u{ffff}".repeat(16);
+        let encoded = encode_message(&msg, &pubkey).unwrap();
+        for i in &encoded {
+            assert!(i < &pubkey.group.p);
+            assert_eq!(BigInt::from(1), i.modpow(&q, &pubkey.group.p));
+        }
+        assert_eq!(
+            Some(msg),
+            decode_message(&encoded, &pubkey.group.p, pubkey.group.bit_length)
+        );
+    }
+
+    #[test]
+    fn test_keys_that_cannot_encode_are_rejected() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        // 32 bit chunks do not fit below q = 11
+        let oversized = PublicKey::<BigInt>::from_hex_str("0x17, 0x5, 0x3, 32");
+        // p = 1 mod 4
+        let one_mod_four = PublicKey::<BigInt>::from_hex_str("0x3b9aca09, 0x2, 0x3, 10");
+        for pubkey in [oversized, one_mod_four] {
+            assert!(!pubkey.group.encodes_messages());
+            assert_eq!(None, String::from("message").encrypt(&pubkey, &mut rng));
+            assert!(crate::stream::StreamEncryptor::new(&pubkey).is_err());
+        }
+        let (pubkey, _) = generate_pub_key(&SEED, 32, 32);
+        assert!(pubkey.group.encodes_messages());
+    }
+
+    #[test]
+    fn test_qr_public_key_generation() {
+        let (pubkey, mut rng) = generate_qr_pub_key(&SEED, 32, 32);
+        let q: BigInt = (&pubkey.group.p - 1) / 2;
+        assert_ne!(BigInt::from(1), pubkey.group.g);
+        assert_eq!(BigInt::from(1), pubkey.group.g.modpow(&q, &pubkey.group.p));
+        assert!(utils::is_quadratic_residue(&pubkey.h, &pubkey.group.p));
+        let (pubkey, private_key) = generate_key_pair(&pubkey.group, &mut rng);
+        let msg = String::from("qr subgroup message");
+        let cipher = msg.encrypt(&pubkey, &mut rng).unwrap();
+        // every c and d of the ciphertext stays inside the subgroup
+        for num in cipher.split_whitespace() {
+            let num = BigInt::from_str_radix(num, 10).unwrap();
+            assert!(utils::is_quadratic_residue(&num, &pubkey.group.p));
+        }
+        assert_eq!(Some(msg), cipher.decrypt(&private_key));
+    }
+
+    #[test]
+    fn test_versioned_seed_of_large_key() {
+        let mut rng: mt19937::MT19937 = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let (pubkey, _) = groups::StandardGroup::Modp1536.generate_key_pair(&mut rng);
+        let seed = pubkey.derive_seed(SeedVersion::V1, &[7u8; 32]);
+        assert_eq!(8, seed.len());
+        assert_ne!(seed, pubkey.derive_seed(SeedVersion::V1, &[8u8; 32]));
+        assert_eq!(Err(RawKeyError), RawPublicKey::try_from(pubkey));
+    }
+
+    #[test]
+    fn test_versioned_seed_derivation() {
+        let pubkey = PublicKey::<BigInt>::from_hex_str("0x747c85d7, 0x747c85d6, 0xb2040843, 32");
+        let raw_key = pubkey.clone().to_raw();
+        let block_hash = [7u8; 32];
+        // v0 is the legacy p + g + h derivation and ignores the entropy
+        assert_eq!(
+            pubkey.yield_seed_slice(),
+            pubkey.derive_seed(SeedVersion::V0, &block_hash)
+        );
+        assert_eq!(
+            pubkey.yield_pubkey(32).to_raw(),
+            raw_key.yield_pubkey_with_entropy(32, SeedVersion::V0, &block_hash)
+        );
+        // v1 hashes the key together with the entropy, raw and big integer keys agree
+        let seed = raw_key.derive_seed(SeedVersion::V1, &block_hash);
+        assert_eq!(8, seed.len());
+        assert_eq!(seed, pubkey.derive_seed(SeedVersion::V1, &block_hash));
+        assert_ne!(seed, raw_key.derive_seed(SeedVersion::V1, &[8u8; 32]));
+        let next_key = pubkey.yield_pubkey_with_entropy(32, SeedVersion::V1, &block_hash);
+        let next_raw = raw_key.yield_pubkey_with_entropy(32, SeedVersion::V1, &block_hash);
+        assert_eq!(next_key.clone().to_raw(), next_raw);
+        // the raw chain continues without any registry
+        assert_eq!(
+            next_key.yield_pubkey(32).to_raw(),
+            next_raw.yield_pubkey(32)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decode_inverts_encode(msg in any::<String>(), bit_length in 10u32..=4096) {
+            let encoded = encode_utf16(&msg, bit_length);
+            // two bits of headroom below 2^(n-2) <= q
+            prop_assert!(encoded.iter().all(|i| i.bits() <= (bit_length - 2) as u64));
+            prop_assert_eq!(Some(msg), decode_utf16(&encoded, bit_length));
+        }
+
+        #[test]
+        fn prop_key_hex_str_round_trip(
+            p in any::<Vec<u8>>(),
+            g in any::<Vec<u8>>(),
+            k in any::<Vec<u8>>(),
+            bit_length in any::<u32>(),
+        ) {
+            let int = |bytes: &[u8]| BigInt::from_bytes_be(num_bigint::Sign::Plus, bytes);
+            let group = Arc::new(GroupParams::new(int(&p), int(&g), bit_length));
+            let pubkey = group.public_key(int(&k));
+            let hex_str = pubkey.to_hex_str();
+            let parsed = PublicKey::<BigInt>::from_hex_str(&hex_str);
+            prop_assert_eq!(&pubkey.group, &parsed.group);
+            prop_assert_eq!(&pubkey.h, &parsed.h);
+            // upper case, no 0x prefix and extra whitespace
+            let loose = hex_str.to_uppercase().replace("0X", "").replace(", ", " ,\t");
+            let loose = format!(" {} ", loose);
+            let parsed = PublicKey::<BigInt>::try_from_hex_str(&loose).unwrap();
+            prop_assert_eq!(&pubkey.h, &parsed.h);
+            prop_assert_eq!(pubkey.group.bit_length, parsed.group.bit_length);
+
+            let private_key = group.private_key(int(&k));
+            let parsed: PrivateKey<BigInt> = private_key.to_string().parse().unwrap();
+            prop_assert_eq!(&private_key.group, &parsed.group);
+            prop_assert_eq!(&private_key.x, &parsed.x);
+        }
+
+        #[test]
+        fn prop_ciphertext_str_round_trip(
+            pairs in prop::collection::vec((any::<Vec<u8>>(), any::<Vec<u8>>()), 1..8),
+        ) {
+            let int = |bytes: &[u8]| BigInt::from_bytes_be(num_bigint::Sign::Plus, bytes);
+            let cipher = Ciphertext {
+                pairs: pairs.iter().map(|(c, d)| (int(c), int(d))).collect(),
+            };
+            let parsed: Ciphertext = cipher.to_string().parse().unwrap();
+            prop_assert_eq!(cipher, parsed);
+        }
     }
 }
@@ -0,0 +1,194 @@
+//! precompute mod
+//! fixed-base exponentiation tables for encrypting many messages to one public key.
+//! g and h never change for a given key, so their powers can be tabled once and
+//! every later g^y, h^y is a handful of table lookups and multiplications.
+use crate::elgamal::{encode_message, encrypt_codes, format_cipher_pairs};
+use crate::generic::PublicKey;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand_core::RngCore;
+
+/// Default window width in bits of the fixed-base tables.
+pub const DEFAULT_WINDOW: u32 = 4;
+
+/// Fixed-base windowed exponentiation table for `base` modulo `modulus`.
+/// # Logic Desc
+/// ```text
+/// w is the window width, e = sum(e[i] * 2^(w*i)) with 0 <= e[i] < 2^w
+/// rows[i][j] = base^(j * 2^(w*i)) mod p
+/// base^e = product of rows[i][e[i]] mod p
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedBaseTable {
+    modulus: BigInt,
+    window: u32,
+    rows: Vec<Vec<BigInt>>,
+}
+
+impl FixedBaseTable {
+    /// Build a table covering every exponent of at most `max_bits` bits.
+    pub fn new(base: &BigInt, modulus: &BigInt, max_bits: u64, window: u32) -> Self {
+        assert!(window > 0 && window < 16, "window must be in 1..16 bits");
+        let row_count = max_bits.div_ceil(window as u64).max(1) as usize;
+        let row_len = 1usize << window;
+        let mut rows = Vec::with_capacity(row_count);
+        // b = base^(2^(w*i)) mod p for the current row i
+        let mut b = base.mod_floor(modulus);
+        for _ in 0..row_count {
+            let mut row = Vec::with_capacity(row_len);
+            row.push(BigInt::one());
+            for j in 1..row_len {
+                let next = (&row[j - 1] * &b) % modulus;
+                row.push(next);
+            }
+            b = (&row[row_len - 1] * &b) % modulus;
+            rows.push(row);
+        }
+        FixedBaseTable {
+            modulus: modulus.clone(),
+            window,
+            rows,
+        }
+    }
+
+    /// Largest exponent bit length the table covers.
+    pub fn max_bits(&self) -> u64 {
+        self.rows.len() as u64 * self.window as u64
+    }
+
+    /// Compute base^exp mod p, falling back to `modpow` for exponents beyond the table.
+    pub fn pow(&self, exp: &BigInt) -> BigInt {
+        if exp.is_zero() {
+            return BigInt::one() % &self.modulus;
+        }
+        if exp.sign() == Sign::Minus || exp.bits() > self.max_bits() {
+            let base = &self.rows[0][1];
+            return base.modpow(exp, &self.modulus);
+        }
+        let mut result = BigInt::one();
+        // split exponent into w-bit digits, little endian
+        let mask = (1u64 << self.window) - 1;
+        let digits = exp.to_u64_digits().1;
+        for (i, row) in self.rows.iter().enumerate() {
+            let bit = i as u64 * self.window as u64;
+            let word = (bit / 64) as usize;
+            if word >= digits.len() {
+                break;
+            }
+            let offset = bit % 64;
+            let mut digit = digits[word] >> offset;
+            if offset + self.window as u64 > 64 && word + 1 < digits.len() {
+                digit |= digits[word + 1] << (64 - offset);
+            }
+            let digit = (digit & mask) as usize;
+            if digit != 0 {
+                result = (result * &row[digit]) % &self.modulus;
+            }
+        }
+        result
+    }
+}
+
+/// A public key with fixed-base tables for g and h precomputed.
+/// Encrypting to a prepared key gives exactly the same ciphertext as
+/// `Encryption::encrypt` with the same RNG, only faster.
+#[derive(Debug, Clone)]
+pub struct PreparedPublicKey {
+    key: PublicKey<BigInt>,
+    g_table: FixedBaseTable,
+    h_table: FixedBaseTable,
+}
+
+impl PreparedPublicKey {
+    /// Precompute tables with the default window width.
+    pub fn new(key: PublicKey<BigInt>) -> Self {
+        Self::with_window(key, DEFAULT_WINDOW)
+    }
+
+    /// Precompute tables with a custom window width, wider windows trade memory for speed.
+    pub fn with_window(key: PublicKey<BigInt>, window: u32) -> Self {
+        // random exponents y are drawn from [0, p], so cover every bit of p.
+        let max_bits = key.group.p.bits();
+        let g_table = FixedBaseTable::new(&key.group.g, &key.group.p, max_bits, window);
+        let h_table = FixedBaseTable::new(&key.h, &key.group.p, max_bits, window);
+        PreparedPublicKey {
+            key,
+            g_table,
+            h_table,
+        }
+    }
+
+    /// The underlying public key.
+    pub fn public_key(&self) -> &PublicKey<BigInt> {
+        &self.key
+    }
+
+    /// Compute (g^y mod p, h^y mod p) from the tables.
+    pub fn pow_g_h(&self, y: &BigInt) -> (BigInt, BigInt) {
+        (self.g_table.pow(y), self.h_table.pow(y))
+    }
+
+    /// Encrypts a string using the prepared key, None if the key cannot encode messages.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::precompute::PreparedPublicKey;
+    /// use num_bigint::BigUint;
+    /// let big_num = BigUint::from(2929u32);
+    /// let (pubkey, mut rng) = elgamal::generate_pub_key(&big_num.to_u32_digits(), 32, 32);
+    /// let prepared = PreparedPublicKey::new(pubkey);
+    /// let result = prepared.encrypt("message for encrypt", &mut rng).unwrap();
+    /// ```
+    pub fn encrypt<R: RngCore>(&self, plaintext: &str, rng: &mut R) -> Option<String> {
+        let z = encode_message(plaintext, &self.key)?;
+        let cipher_pairs = encrypt_codes(&z, &self.key.group.p, rng, |y| self.pow_g_h(y));
+        Some(format_cipher_pairs(&cipher_pairs))
+    }
+}
+
+impl From<PublicKey<BigInt>> for PreparedPublicKey {
+    fn from(key: PublicKey<BigInt>) -> Self {
+        PreparedPublicKey::new(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::Encryption;
+    use crate::utils;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_table_pow_matches_modpow() {
+        // wider than one u64 digit so windows straddle digit boundaries
+        let (pubkey, mut rng) = crate::generate_pub_key(&SEED, 96, 16);
+        let group = &pubkey.group;
+        for window in 1..8 {
+            let table = FixedBaseTable::new(&group.g, &group.p, group.p.bits(), window);
+            for _ in 0..32 {
+                let y = utils::gen_bigint_range(&mut rng, &BigInt::from(0), &group.p);
+                assert_eq!(table.pow(&y), group.g.modpow(&y, &group.p));
+            }
+            // exponents beyond the table fall back to modpow
+            let big = &group.p * &group.p;
+            assert_eq!(table.pow(&big), group.g.modpow(&big, &group.p));
+        }
+    }
+
+    #[test]
+    fn test_prepared_encrypt_matches_encrypt() {
+        let (pubkey, _) = crate::generate_pub_key(&SEED, 64, 16);
+        let prepared = PreparedPublicKey::new(pubkey.clone());
+        let msg = String::from("prepared message");
+        let mut rng_a = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let mut rng_b = mt19937::MT19937::new_with_slice_seed(&SEED);
+        assert_eq!(
+            msg.encrypt(&pubkey, &mut rng_a),
+            prepared.encrypt(&msg, &mut rng_b)
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! proofs mod
+//! zero knowledge proofs over elgamal groups, made non-interactive with the
+//! Fiat-Shamir transform: the verifier's random challenge is replaced by a
+//! SHA-256 hash of a domain tag, the caller's context and every public value.
+use crate::elgamal::{
+    decode_message, encode_message, format_cipher_pairs, parse_cipher_pairs, random_exponent,
+    unblind,
+};
+use crate::generic::{PrivateKey, PublicKey};
+use crate::groups::GroupParams;
+use crate::utils;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use rand_core::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Hashes a domain tag, a context and group elements into a challenge in [0, order).
+/// Every input is length prefixed, so different splits of the same bytes never collide.
+pub fn challenge(domain: &[u8], context: &[u8], elements: &[&BigInt], order: &BigInt) -> BigInt {
+    let mut hasher = Sha256::new();
+    for bytes in [domain, context] {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    }
+    for element in elements {
+        let bytes = element.to_bytes_be().1;
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(&bytes);
+    }
+    BigInt::from_bytes_be(Sign::Plus, &hasher.finalize()).mod_floor(order)
+}
+
+/// Checks that a group element lies in [1, p - 1].
+pub(crate) fn in_group(element: &BigInt, p: &BigInt) -> bool {
+    element > &BigInt::from(0) && element < p
+}
+
+/// Checks that g generates the order q subgroup of quadratic residues mod p = 2q + 1.
+pub(crate) fn is_subgroup_generator(g: &BigInt, p: &BigInt, q: &BigInt) -> bool {
+    g > &BigInt::from(1) && g < p && g.modpow(q, p) == BigInt::from(1)
+}
+
+/// Checks that a group element lies in [1, p - 1] and in the subgroup of the given order.
+pub(crate) fn in_subgroup(element: &BigInt, p: &BigInt, order: &BigInt) -> bool {
+    in_group(element, p) && element.modpow(order, p) == BigInt::from(1)
+}
+
+/// Statement for a Chaum-Pedersen proof: log_g(h) = log_c(s) mod p.
+///
+/// `order` must be the prime order q of the subgroup g generates; the verifier rejects
+/// any element outside it, so a composite order cannot leak the witness modulo its factors.
+#[derive(Debug, Clone, Copy)]
+pub struct DleqStatement<'a> {
+    pub p: &'a BigInt,
+    pub order: &'a BigInt,
+    pub g: &'a BigInt,
+    pub h: &'a BigInt,
+    pub c: &'a BigInt,
+    pub s: &'a BigInt,
+}
+
+/// Chaum-Pedersen proof of equality of discrete logarithms.
+/// # Logic Desc
+/// ```text
+/// prover knows x with h = g^x and s = c^x
+/// w is random in [0, order - 1]
+/// a = g^w mod p, b = c^w mod p
+/// e = H(domain, context, p, g, h, c, s, a, b) mod order
+/// z = w + e*x mod order
+/// verifier checks g^z = a*h^e mod p and c^z = b*s^e mod p
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChaumPedersenProof {
+    pub a: BigInt,
+    pub b: BigInt,
+    pub z: BigInt,
+}
+
+impl ChaumPedersenProof {
+    const DOMAIN: &'static [u8] = b"elgamal_wasm/chaum-pedersen";
+
+    /// Proves the statement with the witness x, bound to the caller's context.
+    pub fn prove<R: RngCore>(
+        statement: &DleqStatement,
+        x: &BigInt,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let DleqStatement { p, order, g, c, .. } = *statement;
+        let w = utils::gen_bigint_range(rng, &BigInt::from(0), &(order - 1u32));
+        let a = g.modpow(&w, p);
+        let b = c.modpow(&w, p);
+        let e = Self::challenge(statement, &a, &b, context);
+        let z = (w + e * x).mod_floor(order);
+        ChaumPedersenProof { a, b, z }
+    }
+
+    /// Verifies the proof against the statement and context.
+    pub fn verify(&self, statement: &DleqStatement, context: &[u8]) -> bool {
+        let DleqStatement {
+            p,
+            order,
+            g,
+            h,
+            c,
+            s,
+        } = *statement;
+        if ![h, c, s, &self.a, &self.b]
+            .iter()
+            .all(|x| in_subgroup(x, p, order))
+        {
+            return false;
+        }
+        if self.z < BigInt::from(0) || &self.z >= order {
+            return false;
+        }
+        let e = Self::challenge(statement, &self.a, &self.b, context);
+        g.modpow(&self.z, p) == (&self.a * h.modpow(&e, p)).mod_floor(p)
+            && c.modpow(&self.z, p) == (&self.b * s.modpow(&e, p)).mod_floor(p)
+    }
+
+    fn challenge(statement: &DleqStatement, a: &BigInt, b: &BigInt, context: &[u8]) -> BigInt {
+        let DleqStatement {
+            p,
+            order,
+            g,
+            h,
+            c,
+            s,
+        } = *statement;
+        challenge(Self::DOMAIN, context, &[p, g, h, c, s, a, b], order)
+    }
+}
+
+/// Statement for a Schnorr proof: the prover knows y with c = g^y mod p.
+///
+/// As for `DleqStatement`, `order` must be the prime order q of the subgroup g generates.
+#[derive(Debug, Clone, Copy)]
+pub struct DlogStatement<'a> {
+    pub p: &'a BigInt,
+    pub order: &'a BigInt,
+    pub g: &'a BigInt,
+    pub c: &'a BigInt,
+}
+
+/// Schnorr proof of knowledge of a discrete logarithm.
+/// # Logic Desc
+/// ```text
+/// prover knows y with c = g^y
+/// w is random in [0, order - 1]
+/// a = g^w mod p
+/// e = H(domain, context, p, g, c, a) mod order
+/// z = w + e*y mod order
+/// verifier checks g^z = a*c^e mod p
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchnorrProof {
+    pub a: BigInt,
+    pub z: BigInt,
+}
+
+impl SchnorrProof {
+    const DOMAIN: &'static [u8] = b"elgamal_wasm/schnorr";
+
+    /// Proves knowledge of y, bound to the caller's context.
+    pub fn prove<R: RngCore>(
+        statement: &DlogStatement,
+        y: &BigInt,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let DlogStatement { p, order, g, .. } = *statement;
+        let w = utils::gen_bigint_range(rng, &BigInt::from(0), &(order - 1u32));
+        let a = g.modpow(&w, p);
+        let e = Self::challenge(statement, &a, context);
+        let z = (w + e * y).mod_floor(order);
+        SchnorrProof { a, z }
+    }
+
+    /// Verifies the proof against the statement and context.
+    pub fn verify(&self, statement: &DlogStatement, context: &[u8]) -> bool {
+        let DlogStatement { p, order, g, c } = *statement;
+        if !in_subgroup(c, p, order) || !in_subgroup(&self.a, p, order) {
+            return false;
+        }
+        if self.z < BigInt::from(0) || &self.z >= order {
+            return false;
+        }
+        let e = Self::challenge(statement, &self.a, context);
+        g.modpow(&self.z, p) == (&self.a * c.modpow(&e, p)).mod_floor(p)
+    }
+
+    fn challenge(statement: &DlogStatement, a: &BigInt, context: &[u8]) -> BigInt {
+        let DlogStatement { p, order, g, c } = *statement;
+        challenge(Self::DOMAIN, context, &[p, g, c, a], order)
+    }
+}
+
+/// Proof that the encryptor knows the randomness y of every cipher pair (c, d) = (g^y, m*h^y),
+/// and hence the plaintext. Each Schnorr proof is bound to the caller's context, the key,
+/// d and the pair's position, so a ciphertext copied from someone else cannot be resubmitted
+/// with a fresh proof, and a proof cannot be replayed under another context.
+/// Only keys whose g generates the order q subgroup of quadratic residues are accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaintextKnowledgeProof {
+    pub proofs: Vec<SchnorrProof>,
+}
+
+impl PlaintextKnowledgeProof {
+    const CONTEXT: &'static [u8] = b"elgamal_wasm/plaintext-knowledge";
+
+    /// Verifies the proof for `cipher` under `key` and the context given to the encryptor.
+    pub fn verify(&self, key: &PublicKey<BigInt>, cipher: &str, context: &[u8]) -> bool {
+        let cipher_pairs = match parse_cipher_pairs(cipher) {
+            Some(cipher_pairs) => cipher_pairs,
+            None => return false,
+        };
+        if self.proofs.len() != cipher_pairs.len() {
+            return false;
+        }
+        if !is_subgroup_generator(&key.group.g, &key.group.p, &key.group.q) {
+            return false;
+        }
+        let count = cipher_pairs.len();
+        cipher_pairs
+            .iter()
+            .zip(self.proofs.iter())
+            .enumerate()
+            .all(|(index, ((c, d), proof))| {
+                let statement = DlogStatement {
+                    p: &key.group.p,
+                    order: &key.group.q,
+                    g: &key.group.g,
+                    c,
+                };
+                let pair_context = Self::pair_context(context, key, d, index, count);
+                proof.verify(&statement, &pair_context)
+            })
+    }
+
+    fn pair_context(
+        context: &[u8],
+        key: &PublicKey<BigInt>,
+        d: &BigInt,
+        index: usize,
+        count: usize,
+    ) -> Vec<u8> {
+        let mut pair_context = Self::CONTEXT.to_vec();
+        pair_context.extend((context.len() as u64).to_le_bytes());
+        pair_context.extend(context);
+        for element in [&key.h, d] {
+            let bytes = element.to_bytes_be().1;
+            pair_context.extend((bytes.len() as u64).to_le_bytes());
+            pair_context.extend(bytes);
+        }
+        pair_context.extend((index as u64).to_le_bytes());
+        pair_context.extend((count as u64).to_le_bytes());
+        pair_context
+    }
+}
+
+/// A trait to encrypt plaintext with a proof of plaintext knowledge attached.
+pub trait ProvableEncryption {
+    fn encrypt_with_proof<R: RngCore>(
+        &self,
+        key: &PublicKey<BigInt>,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Option<(String, PlaintextKnowledgeProof)>;
+}
+
+impl ProvableEncryption for String {
+    /// Encrypts like `Encryption::encrypt` and proves knowledge of every y.
+    /// Returns None if the key cannot encode messages or its g does not generate the
+    /// quadratic residue subgroup.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::proofs::ProvableEncryption;
+    /// let (pubkey, mut rng) = elgamal::generate_qr_pub_key(&[2929], 32, 32);
+    /// let (cipher, proof) = String::from("bid: 100").encrypt_with_proof(&pubkey, b"auction 7", &mut rng).unwrap();
+    /// assert!(proof.verify(&pubkey, &cipher, b"auction 7"));
+    /// ```
+    fn encrypt_with_proof<R: RngCore>(
+        &self,
+        key: &PublicKey<BigInt>,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Option<(String, PlaintextKnowledgeProof)> {
+        let z = encode_message(self, key)?;
+        let GroupParams { p, q, g, .. } = &*key.group;
+        if !is_subgroup_generator(g, p, q) {
+            return None;
+        }
+        let count = z.len();
+        let mut cipher_pairs = Vec::with_capacity(count);
+        let mut proofs = Vec::with_capacity(count);
+        for (index, i_code) in z.iter().enumerate() {
+            let y = random_exponent(rng, p);
+            let c = g.modpow(&y, p);
+            let d = (i_code * key.h.modpow(&y, p)) % p;
+            let statement = DlogStatement {
+                p,
+                order: q,
+                g,
+                c: &c,
+            };
+            let pair_context =
+                PlaintextKnowledgeProof::pair_context(context, key, &d, index, count);
+            proofs.push(SchnorrProof::prove(&statement, &y, &pair_context, rng));
+            cipher_pairs.push((c, d));
+        }
+        Some((
+            format_cipher_pairs(&cipher_pairs),
+            PlaintextKnowledgeProof { proofs },
+        ))
+    }
+}
+
+/// Proof that a plaintext is the correct decryption of a ciphertext, one Chaum-Pedersen proof
+/// per cipher pair that s = c^x uses the same x as h = g^x.
+/// # Logic Desc
+/// ```text
+/// the verifier encodes the claimed plaintext to m[i] and computes s[i] = d[i] * m[i]^-1 mod p
+/// proofs[i] shows log_g(h) = log_c[i](s[i]), exponents are taken mod q
+/// ```
+/// Only keys whose g generates the order q subgroup of quadratic residues can prove
+/// decryptions; c[i] and s[i] must lie in that subgroup too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptionProof {
+    pub proofs: Vec<ChaumPedersenProof>,
+}
+
+impl DecryptionProof {
+    const CONTEXT: &'static [u8] = b"elgamal_wasm/decryption";
+
+    /// Verifies that `plaintext` is the decryption of `cipher` under the private key behind `key`.
+    pub fn verify(&self, key: &PublicKey<BigInt>, cipher: &str, plaintext: &str) -> bool {
+        let cipher_pairs = match parse_cipher_pairs(cipher) {
+            Some(cipher_pairs) => cipher_pairs,
+            None => return false,
+        };
+        let encoded = match encode_message(plaintext, key) {
+            Some(encoded) => encoded,
+            None => return false,
+        };
+        if encoded.len() != cipher_pairs.len() || self.proofs.len() != cipher_pairs.len() {
+            return false;
+        }
+        if !is_subgroup_generator(&key.group.g, &key.group.p, &key.group.q) {
+            return false;
+        }
+        let count = cipher_pairs.len();
+        cipher_pairs
+            .iter()
+            .zip(encoded.iter().zip(self.proofs.iter()))
+            .enumerate()
+            .all(|(index, ((c, d), (m, proof)))| {
+                let s = unblind(d, m, &key.group.p);
+                let statement = DleqStatement {
+                    p: &key.group.p,
+                    order: &key.group.q,
+                    g: &key.group.g,
+                    h: &key.h,
+                    c,
+                    s: &s,
+                };
+                proof.verify(&statement, &Self::pair_context(&key.h, d, index, count))
+            })
+    }
+
+    fn pair_context(h: &BigInt, d: &BigInt, index: usize, count: usize) -> Vec<u8> {
+        let mut pair_context = Self::CONTEXT.to_vec();
+        for element in [h, d] {
+            let bytes = element.to_bytes_be().1;
+            pair_context.extend((bytes.len() as u64).to_le_bytes());
+            pair_context.extend(bytes);
+        }
+        pair_context.extend((index as u64).to_le_bytes());
+        pair_context.extend((count as u64).to_le_bytes());
+        pair_context
+    }
+}
+
+/// A trait to decrypt to plaintext together with a proof that anyone holding the
+/// public key can check.
+pub trait VerifiableDecryption {
+    fn decrypt_with_proof<R: RngCore>(
+        &self,
+        key: &PrivateKey<BigInt>,
+        rng: &mut R,
+    ) -> Option<(String, DecryptionProof)>;
+}
+
+impl VerifiableDecryption for String {
+    /// Decrypts like `Decryption::decrypt` and proves every s = c^x.
+    /// Returns None unless g generates the quadratic residue subgroup and every c lies in it.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::generic::Encryption;
+    /// use elgamal::proofs::VerifiableDecryption;
+    /// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 32, 32);
+    /// let (pubkey, private_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+    /// let cipher = String::from("message").encrypt(&pubkey, &mut rng).unwrap();
+    /// let (plaintext, proof) = cipher.decrypt_with_proof(&private_key, &mut rng).unwrap();
+    /// assert!(proof.verify(&pubkey, &cipher, &plaintext));
+    /// ```
+    fn decrypt_with_proof<R: RngCore>(
+        &self,
+        key: &PrivateKey<BigInt>,
+        rng: &mut R,
+    ) -> Option<(String, DecryptionProof)> {
+        let cipher_pairs = parse_cipher_pairs(self)?;
+        let GroupParams { p, q, g, .. } = &*key.group;
+        if !is_subgroup_generator(g, p, q)
+            || !cipher_pairs.iter().all(|(c, _)| in_subgroup(c, p, q))
+        {
+            return None;
+        }
+        let h = g.modpow(&key.x, p);
+        let count = cipher_pairs.len();
+        let mut plain_text = Vec::with_capacity(count);
+        let mut proofs = Vec::with_capacity(count);
+        for (index, (c, d)) in cipher_pairs.iter().enumerate() {
+            let s = c.modpow(&key.x, p);
+            let statement = DleqStatement {
+                p,
+                order: q,
+                g,
+                h: &h,
+                c,
+                s: &s,
+            };
+            proofs.push(ChaumPedersenProof::prove(
+                &statement,
+                &key.x,
+                &DecryptionProof::pair_context(&h, d, index, count),
+                rng,
+            ));
+            plain_text.push(unblind(d, &s, p));
+        }
+        let plaintext = decode_message(&plain_text, p, key.group.bit_length)?;
+        Some((plaintext, DecryptionProof { proofs }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_chaum_pedersen_proof() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let group = &group_key.group;
+        let q = &group.q;
+        let x = BigInt::from(987654321u64);
+        let h = group.g.modpow(&x, &group.p);
+        let c = group_key.h.clone();
+        let s = c.modpow(&x, &group.p);
+        let statement = DleqStatement {
+            p: &group.p,
+            order: q,
+            g: &group.g,
+            h: &h,
+            c: &c,
+            s: &s,
+        };
+        let proof = ChaumPedersenProof::prove(&statement, &x, b"context", &mut rng);
+        assert!(proof.verify(&statement, b"context"));
+        // the proof is bound to its context
+        assert!(!proof.verify(&statement, b"other context"));
+        // and to s
+        let wrong_s = (&s * &group.g) % &group.p;
+        let wrong = DleqStatement {
+            s: &wrong_s,
+            ..statement
+        };
+        assert!(!proof.verify(&wrong, b"context"));
+    }
+
+    #[test]
+    fn test_verifiable_decryption() {
+        use crate::generic::Encryption;
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (pubkey, private_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let msg = String::from("verifiable decryption");
+        let cipher = msg.encrypt(&pubkey, &mut rng).unwrap();
+        let (plaintext, proof) = cipher.decrypt_with_proof(&private_key, &mut rng).unwrap();
+        assert_eq!(msg, plaintext);
+        assert!(proof.verify(&pubkey, &cipher, &plaintext));
+        assert!(!proof.verify(&pubkey, &cipher, "verifiable decryptioN"));
+        let (other_key, _) = crate::generate_key_pair(&group_key.group, &mut rng);
+        assert!(!proof.verify(&other_key, &cipher, &plaintext));
+        // proofs are bound to the pair position
+        let mut swapped = DecryptionProof {
+            proofs: proof.proofs.clone(),
+        };
+        swapped.proofs.reverse();
+        assert!(!swapped.verify(&pubkey, &cipher, &plaintext));
+        // each proof holds for its pair, and only at the pair's own index
+        let cipher_pairs = parse_cipher_pairs(&cipher).unwrap();
+        let encoded = encode_message(&plaintext, &pubkey).unwrap();
+        let count = cipher_pairs.len();
+        assert!(count > 1);
+        for (index, ((c, d), m)) in cipher_pairs.iter().zip(encoded.iter()).enumerate() {
+            let s = unblind(d, m, &pubkey.group.p);
+            let statement = DleqStatement {
+                p: &pubkey.group.p,
+                order: &pubkey.group.q,
+                g: &pubkey.group.g,
+                h: &pubkey.h,
+                c,
+                s: &s,
+            };
+            let pair_proof = &proof.proofs[index];
+            let context = DecryptionProof::pair_context(&pubkey.h, d, index, count);
+            assert!(pair_proof.verify(&statement, &context));
+            let moved = count - 1 - index;
+            if moved != index {
+                let context = DecryptionProof::pair_context(&pubkey.h, d, moved, count);
+                assert!(!pair_proof.verify(&statement, &context));
+            }
+        }
+    }
+
+    #[test]
+    fn test_verifiable_decryption_needs_subgroup_key() {
+        use crate::generic::Encryption;
+        let (group_key, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        let (pubkey, private_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let cipher = String::from("primitive root")
+            .encrypt(&pubkey, &mut rng)
+            .unwrap();
+        assert_eq!(None, cipher.decrypt_with_proof(&private_key, &mut rng));
+    }
+
+    #[test]
+    fn test_plaintext_knowledge_proof() {
+        use crate::generic::Decryption;
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (pubkey, private_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let msg = String::from("sealed bid: 100");
+        let (cipher, proof) = msg
+            .encrypt_with_proof(&pubkey, b"auction 7", &mut rng)
+            .unwrap();
+        assert!(proof.verify(&pubkey, &cipher, b"auction 7"));
+        assert_eq!(Some(msg), cipher.decrypt(&private_key));
+        // replayed under another context
+        assert!(!proof.verify(&pubkey, &cipher, b"auction 8"));
+        // mauled d
+        let mut cipher_pairs = parse_cipher_pairs(&cipher).unwrap();
+        cipher_pairs[0].1 = (&cipher_pairs[0].1 * 2) % &pubkey.group.p;
+        assert!(!proof.verify(&pubkey, &format_cipher_pairs(&cipher_pairs), b"auction 7"));
+    }
+
+    #[test]
+    fn test_plaintext_knowledge_proof_needs_subgroup_key() {
+        let (group_key, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        let (pubkey, _) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let msg = String::from("sealed bid: 100");
+        assert_eq!(
+            None,
+            msg.encrypt_with_proof(&pubkey, b"auction 7", &mut rng)
+        );
+    }
+}
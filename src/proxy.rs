@@ -0,0 +1,205 @@
+//! proxy mod
+//! BBS98 proxy re-encryption over plain elgamal ciphertexts in the quadratic residue
+//! subgroup of a safe prime p = 2q + 1. the re-encryption key a / b mod q turns every
+//! pair (g^y, m * g^(a*y)) for the delegator key a into (g^(a*y/b), m * g^(a*y)), an
+//! ordinary ciphertext for the delegatee key b, so the delegatee decrypts with
+//! `Decryption` and the proxy never sees m.
+//! the scheme is bidirectional: the same key inverted re-encrypts from b to a, and a
+//! proxy colluding with the delegatee can compute a.
+use crate::elgamal::{format_cipher_pairs, parse_cipher_pairs};
+use crate::generic::PrivateKey;
+use crate::proofs::{in_group, is_subgroup_generator};
+use crate::utils;
+use num_bigint::BigInt;
+use num_integer::Integer;
+
+/// Errors of re-encryption key generation and re-encryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyError {
+    /// The keys are not on the same group (p, g).
+    GroupMismatch,
+    /// g is not a quadratic residue, so exponents cannot be inverted mod q.
+    InvalidGenerator,
+    /// A private exponent or blinding factor is 0 mod q.
+    InvalidKey,
+    /// The ciphertext is malformed or not under the group of the re-encryption key.
+    InvalidCiphertext,
+}
+
+/// Type alias for proxy results.
+pub type ProxyResult<T> = std::result::Result<T, ProxyError>;
+
+/// Re-encryption key a / b mod q from the delegator a to the delegatee b.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReEncryptionKey {
+    pub p: BigInt,
+    pub g: BigInt,
+    pub rk: BigInt,
+}
+
+impl ReEncryptionKey {
+    /// Computes the key from both private keys, for a delegator that holds both.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::generic::{Decryption, Encryption};
+    /// use elgamal::proxy::ReEncryptionKey;
+    /// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 64, 16);
+    /// let (alice, alice_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+    /// let (_, bob_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+    /// let rk = ReEncryptionKey::new(&alice_key, &bob_key).unwrap();
+    /// let cipher = String::from("shared file").encrypt(&alice, &mut rng).unwrap();
+    /// let cipher = rk.re_encrypt(&cipher).unwrap();
+    /// assert_eq!(Some(String::from("shared file")), cipher.decrypt(&bob_key));
+    /// ```
+    pub fn new(from: &PrivateKey<BigInt>, to: &PrivateKey<BigInt>) -> ProxyResult<Self> {
+        check_group(from, to)?;
+        ReEncryptionKey::from_shares(&from.group.p, &from.group.g, &from.x, &to.x)
+    }
+
+    /// Combines blinded exponents a * t and b * t mod q into a / b, so the proxy gets the
+    /// key without learning a or b. The delegator picks t at random in [1, q - 1] and
+    /// gives it to the delegatee over a private channel, only these two ever see t.
+    /// Each of them sends its `rekey_share` to the proxy alone over a private channel,
+    /// the shares are never published: anyone holding t and a * t can compute a.
+    pub fn from_shares(
+        p: &BigInt,
+        g: &BigInt,
+        from_share: &BigInt,
+        to_share: &BigInt,
+    ) -> ProxyResult<Self> {
+        let q: BigInt = (p - 1u32) / 2u32;
+        if !is_subgroup_generator(g, p, &q) {
+            return Err(ProxyError::InvalidGenerator);
+        }
+        let (from_share, to_share) = (from_share.mod_floor(&q), to_share.mod_floor(&q));
+        if from_share == BigInt::from(0) || to_share == BigInt::from(0) {
+            return Err(ProxyError::InvalidKey);
+        }
+        Ok(ReEncryptionKey {
+            p: p.clone(),
+            g: g.clone(),
+            rk: (from_share * utils::mod_inverse(&to_share, &q)).mod_floor(&q),
+        })
+    }
+
+    /// The key b / a re-encrypting in the opposite direction.
+    pub fn invert(&self) -> Self {
+        let q: BigInt = (&self.p - 1u32) / 2u32;
+        ReEncryptionKey {
+            p: self.p.clone(),
+            g: self.g.clone(),
+            rk: utils::mod_inverse(&self.rk, &q),
+        }
+    }
+
+    /// Re-encrypts every pair (c, d) of a cipher string to (c^rk mod p, d).
+    pub fn re_encrypt(&self, cipher: &str) -> ProxyResult<String> {
+        let pairs = parse_cipher_pairs(cipher).ok_or(ProxyError::InvalidCiphertext)?;
+        let pairs = pairs
+            .into_iter()
+            .map(|(c, d)| {
+                if !in_group(&c, &self.p) || !in_group(&d, &self.p) {
+                    return Err(ProxyError::InvalidCiphertext);
+                }
+                Ok((c.modpow(&self.rk, &self.p), d))
+            })
+            .collect::<ProxyResult<Vec<_>>>()?;
+        Ok(format_cipher_pairs(&pairs))
+    }
+}
+
+/// Blinds the private exponent of the key, x * t mod q, see `ReEncryptionKey::from_shares`.
+pub fn rekey_share(key: &PrivateKey<BigInt>, t: &BigInt) -> BigInt {
+    let q: BigInt = (&key.group.p - 1u32) / 2u32;
+    (&key.x * t).mod_floor(&q)
+}
+
+/// Checks that both keys share (p, g).
+fn check_group(from: &PrivateKey<BigInt>, to: &PrivateKey<BigInt>) -> ProxyResult<()> {
+    if from.group.p != to.group.p || from.group.g != to.group.g {
+        return Err(ProxyError::GroupMismatch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::{Decryption, Encryption, PublicKey};
+
+    const SEED: [u32; 1] = [2929];
+
+    type KeyPair = (PublicKey<BigInt>, PrivateKey<BigInt>);
+
+    fn key_pairs(n: usize) -> (Vec<KeyPair>, mt19937::MT19937) {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let pairs = (0..n)
+            .map(|_| crate::generate_key_pair(&group_key.group, &mut rng))
+            .collect();
+        (pairs, rng)
+    }
+
+    #[test]
+    fn test_re_encrypt_chain() {
+        let (keys, mut rng) = key_pairs(3);
+        let (alice, alice_key) = &keys[0];
+        let (_, bob_key) = &keys[1];
+        let (_, carol_key) = &keys[2];
+        let message = String::from("re-encrypted twice");
+        let cipher = message.encrypt(alice, &mut rng).unwrap();
+
+        let alice_to_bob = ReEncryptionKey::new(alice_key, bob_key).unwrap();
+        let for_bob = alice_to_bob.re_encrypt(&cipher).unwrap();
+        assert_ne!(cipher, for_bob);
+        assert_eq!(Some(message.clone()), for_bob.decrypt(bob_key));
+        assert_ne!(Some(message.clone()), for_bob.decrypt(alice_key));
+        assert_ne!(Some(message.clone()), cipher.decrypt(bob_key));
+
+        let bob_to_carol = ReEncryptionKey::new(bob_key, carol_key).unwrap();
+        let for_carol = bob_to_carol.re_encrypt(&for_bob).unwrap();
+        assert_eq!(Some(message.clone()), for_carol.decrypt(carol_key));
+
+        let back = alice_to_bob.invert().re_encrypt(&for_bob).unwrap();
+        assert_eq!(Some(message), back.decrypt(alice_key));
+    }
+
+    #[test]
+    fn test_shares_and_errors() {
+        let (keys, mut rng) = key_pairs(2);
+        let (alice_key, bob_key) = (&keys[0].1, &keys[1].1);
+        let q: BigInt = (&alice_key.group.p - 1u32) / 2u32;
+        let t = utils::gen_bigint_range(&mut rng, &BigInt::from(1), &(&q - 1u32));
+        let rk = ReEncryptionKey::from_shares(
+            &alice_key.group.p,
+            &alice_key.group.g,
+            &rekey_share(alice_key, &t),
+            &rekey_share(bob_key, &t),
+        );
+        assert_eq!(ReEncryptionKey::new(alice_key, bob_key), rk);
+
+        let (group_key, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        let (_, legacy_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        assert_eq!(
+            Err(ProxyError::GroupMismatch),
+            ReEncryptionKey::new(alice_key, &legacy_key)
+        );
+        assert_eq!(
+            Err(ProxyError::InvalidGenerator),
+            ReEncryptionKey::new(&legacy_key, &legacy_key)
+        );
+        let mut zero_key = bob_key.clone();
+        zero_key.x = q;
+        assert_eq!(
+            Err(ProxyError::InvalidKey),
+            ReEncryptionKey::new(alice_key, &zero_key)
+        );
+        let rk = rk.unwrap();
+        assert_eq!(
+            Err(ProxyError::InvalidCiphertext),
+            rk.re_encrypt("12 34 56")
+        );
+        assert_eq!(Err(ProxyError::InvalidCiphertext), rk.re_encrypt("0 1 "));
+    }
+}
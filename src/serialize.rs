@@ -0,0 +1,734 @@
+//! serialize mod
+//! serde support for keys and ciphertexts, enabled by the `serde` feature.
+//! every type has a stable shape: a format version, big integers as lowercase
+//! "0x" prefixed hex strings and the bit length where the type has one.
+//! byte strings such as ids, AEAD ciphertexts and EC points and scalars are "0x" prefixed
+//! hex as well.
+use crate::cramer_shoup::{CramerShoupPrivateKey, CramerShoupPublicKey};
+use crate::elgamal::{Ciphertext, RawPublicKey};
+use crate::envelope::{Envelope, Slot};
+use crate::generic::{PrivateKey, PublicKey};
+use crate::groups::{GroupId, GroupParams, RawGroupPublicKey};
+use crate::proxy::ReEncryptionKey;
+use crate::signature::Signature;
+use crate::timelock::Capsule;
+use num_bigint::{BigInt, BigUint, Sign};
+use sp_core::U256;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+/// Version written to and expected in every serialized value.
+pub const FORMAT_VERSION: u32 = 1;
+
+fn to_hex(value: &BigInt) -> String {
+    format!("0x{}", value.to_str_radix(16))
+}
+
+fn from_hex<E: Error>(value: &str) -> Result<BigInt, E> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or_else(|| E::custom(format!("hex integer without 0x prefix: {}", value)))?;
+    BigUint::parse_bytes(digits.as_bytes(), 16)
+        .map(BigInt::from)
+        .ok_or_else(|| E::custom(format!("invalid hex integer: {}", value)))
+}
+
+fn bytes_to_hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", digits)
+}
+
+fn bytes_from_hex<E: Error>(value: &str) -> Result<Vec<u8>, E> {
+    let digits = value
+        .strip_prefix("0x")
+        .filter(|digits| digits.len() % 2 == 0)
+        .ok_or_else(|| E::custom(format!("not 0x prefixed hex bytes: {}", value)))?;
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(E::custom)?;
+            u8::from_str_radix(pair, 16).map_err(E::custom)
+        })
+        .collect()
+}
+
+fn array_from_hex<E: Error>(value: &str) -> Result<[u8; 32], E> {
+    bytes_from_hex::<E>(value)?
+        .try_into()
+        .map_err(|_| E::custom(format!("not 32 bytes of 0x prefixed hex: {}", value)))
+}
+
+fn u256_to_hex(value: &U256) -> String {
+    let mut bytes = [0u8; 32];
+    value.to_little_endian(&mut bytes);
+    to_hex(&BigInt::from_bytes_le(Sign::Plus, &bytes))
+}
+
+fn u256_from_hex<E: Error>(value: &str) -> Result<U256, E> {
+    let int = from_hex::<E>(value)?;
+    if int.bits() > 256 {
+        return Err(E::custom(format!("integer exceeds 256 bits: {}", value)));
+    }
+    Ok(U256::from_little_endian(&int.to_bytes_le().1))
+}
+
+fn check_version<E: Error>(version: u32) -> Result<(), E> {
+    if version != FORMAT_VERSION {
+        return Err(E::custom(format!("unsupported format version {}", version)));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct PublicKeyShape {
+    version: u32,
+    p: String,
+    g: String,
+    h: String,
+    bit_length: u32,
+}
+
+impl Serialize for PublicKey<BigInt> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PublicKeyShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.group.p),
+            g: to_hex(&self.group.g),
+            h: to_hex(&self.h),
+            bit_length: self.group.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey<BigInt> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = PublicKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group = GroupParams::new(from_hex(&shape.p)?, from_hex(&shape.g)?, shape.bit_length);
+        Ok(Arc::new(group).public_key(from_hex(&shape.h)?))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PrivateKeyShape {
+    version: u32,
+    p: String,
+    g: String,
+    x: String,
+    bit_length: u32,
+}
+
+impl Serialize for PrivateKey<BigInt> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PrivateKeyShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.group.p),
+            g: to_hex(&self.group.g),
+            x: to_hex(&self.x),
+            bit_length: self.group.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrivateKey<BigInt> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = PrivateKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group = GroupParams::new(from_hex(&shape.p)?, from_hex(&shape.g)?, shape.bit_length);
+        Ok(Arc::new(group).private_key(from_hex(&shape.x)?))
+    }
+}
+
+/// Same shape as `PublicKey`, integers must fit in 256 bits.
+impl Serialize for RawPublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PublicKeyShape {
+            version: FORMAT_VERSION,
+            p: u256_to_hex(&self.p),
+            g: u256_to_hex(&self.g),
+            h: u256_to_hex(&self.h),
+            bit_length: self.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawPublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = PublicKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        Ok(RawPublicKey {
+            p: u256_from_hex(&shape.p)?,
+            g: u256_from_hex(&shape.g)?,
+            h: u256_from_hex(&shape.h)?,
+            bit_length: shape.bit_length,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GroupParamsShape {
+    version: u32,
+    p: String,
+    q: String,
+    g: String,
+    bit_length: u32,
+}
+
+impl Serialize for GroupParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GroupParamsShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.p),
+            q: to_hex(&self.q),
+            g: to_hex(&self.g),
+            bit_length: self.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// q is checked against p, it is written only for readers of the JSON.
+impl<'de> Deserialize<'de> for GroupParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = GroupParamsShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group = GroupParams::new(from_hex(&shape.p)?, from_hex(&shape.g)?, shape.bit_length);
+        if group.q != from_hex(&shape.q)? {
+            return Err(D::Error::custom("q is not (p - 1) / 2"));
+        }
+        Ok(group)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawGroupPublicKeyShape {
+    version: u32,
+    group: String,
+    h: String,
+}
+
+/// The group id as 32 bytes of hex and h as a hex integer.
+impl Serialize for RawGroupPublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawGroupPublicKeyShape {
+            version: FORMAT_VERSION,
+            group: bytes_to_hex(&self.group),
+            h: to_hex(&BigInt::from_bytes_le(Sign::Plus, &self.h)),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawGroupPublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = RawGroupPublicKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group: GroupId = array_from_hex(&shape.group)?;
+        Ok(RawGroupPublicKey {
+            group,
+            h: from_hex::<D::Error>(&shape.h)?.to_bytes_le().1,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CiphertextShape {
+    version: u32,
+    pairs: Vec<(String, String)>,
+}
+
+impl Serialize for Ciphertext {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CiphertextShape {
+            version: FORMAT_VERSION,
+            pairs: self
+                .pairs
+                .iter()
+                .map(|(c, d)| (to_hex(c), to_hex(d)))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ciphertext {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = CiphertextShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let pairs = shape
+            .pairs
+            .iter()
+            .map(|(c, d)| Ok((from_hex(c)?, from_hex(d)?)))
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Ciphertext { pairs })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SlotShape {
+    recipient: String,
+    c: String,
+    wrapped_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EnvelopeShape {
+    version: u32,
+    slots: Vec<SlotShape>,
+    payload: String,
+}
+
+impl Serialize for Envelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EnvelopeShape {
+            version: FORMAT_VERSION,
+            slots: self
+                .slots
+                .iter()
+                .map(|slot| SlotShape {
+                    recipient: bytes_to_hex(&slot.recipient),
+                    c: to_hex(&slot.c),
+                    wrapped_key: bytes_to_hex(&slot.wrapped_key),
+                })
+                .collect(),
+            payload: bytes_to_hex(&self.payload),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Envelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = EnvelopeShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let slots = shape
+            .slots
+            .iter()
+            .map(|slot| {
+                Ok(Slot {
+                    recipient: array_from_hex(&slot.recipient)?,
+                    c: from_hex(&slot.c)?,
+                    wrapped_key: array_from_hex(&slot.wrapped_key)?,
+                })
+            })
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Envelope {
+            slots,
+            payload: bytes_from_hex(&shape.payload)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CapsuleShape {
+    version: u32,
+    head: RawPublicKey,
+    steps: u32,
+    bit_length: u32,
+    target: RawPublicKey,
+    envelope: Envelope,
+}
+
+/// The chain keys and the envelope keep their own shapes.
+impl Serialize for Capsule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CapsuleShape {
+            version: FORMAT_VERSION,
+            head: self.head.clone(),
+            steps: self.steps,
+            bit_length: self.bit_length,
+            target: self.target.clone(),
+            envelope: self.envelope.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Capsule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = CapsuleShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        Ok(Capsule {
+            head: shape.head,
+            steps: shape.steps,
+            bit_length: shape.bit_length,
+            target: shape.target,
+            envelope: shape.envelope,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CramerShoupPublicKeyShape {
+    version: u32,
+    p: String,
+    g: String,
+    g2: String,
+    c: String,
+    d: String,
+    h: String,
+    bit_length: u32,
+}
+
+/// g of the group is g1.
+impl Serialize for CramerShoupPublicKey<BigInt> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CramerShoupPublicKeyShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.group.p),
+            g: to_hex(&self.group.g),
+            g2: to_hex(&self.g2),
+            c: to_hex(&self.c),
+            d: to_hex(&self.d),
+            h: to_hex(&self.h),
+            bit_length: self.group.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CramerShoupPublicKey<BigInt> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = CramerShoupPublicKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group = GroupParams::new(from_hex(&shape.p)?, from_hex(&shape.g)?, shape.bit_length);
+        Ok(CramerShoupPublicKey {
+            group: Arc::new(group),
+            g2: from_hex(&shape.g2)?,
+            c: from_hex(&shape.c)?,
+            d: from_hex(&shape.d)?,
+            h: from_hex(&shape.h)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CramerShoupPrivateKeyShape {
+    version: u32,
+    p: String,
+    g: String,
+    g2: String,
+    x1: String,
+    x2: String,
+    y1: String,
+    y2: String,
+    z: String,
+    bit_length: u32,
+}
+
+impl Serialize for CramerShoupPrivateKey<BigInt> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CramerShoupPrivateKeyShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.group.p),
+            g: to_hex(&self.group.g),
+            g2: to_hex(&self.g2),
+            x1: to_hex(&self.x1),
+            x2: to_hex(&self.x2),
+            y1: to_hex(&self.y1),
+            y2: to_hex(&self.y2),
+            z: to_hex(&self.z),
+            bit_length: self.group.bit_length,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CramerShoupPrivateKey<BigInt> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = CramerShoupPrivateKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        let group = GroupParams::new(from_hex(&shape.p)?, from_hex(&shape.g)?, shape.bit_length);
+        Ok(CramerShoupPrivateKey {
+            group: Arc::new(group),
+            g2: from_hex(&shape.g2)?,
+            x1: from_hex(&shape.x1)?,
+            x2: from_hex(&shape.x2)?,
+            y1: from_hex(&shape.y1)?,
+            y2: from_hex(&shape.y2)?,
+            z: from_hex(&shape.z)?,
+        })
+    }
+}
+
+/// EC keys exist with the `ristretto` or `secp256k1` feature.
+#[cfg(any(feature = "ristretto", feature = "secp256k1"))]
+mod ec_keys {
+    use super::{bytes_from_hex, bytes_to_hex, check_version, FORMAT_VERSION};
+    use crate::ec::{Curve, EcPrivateKey, EcPublicKey};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct EcPublicKeyShape {
+        version: u32,
+        h: String,
+    }
+
+    /// h is the compressed encoding of the point.
+    impl<C: Curve> Serialize for EcPublicKey<C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            EcPublicKeyShape {
+                version: FORMAT_VERSION,
+                h: bytes_to_hex(&C::to_bytes(&self.h)),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, C: Curve> Deserialize<'de> for EcPublicKey<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let shape = EcPublicKeyShape::deserialize(deserializer)?;
+            check_version(shape.version)?;
+            let h = C::from_bytes(&bytes_from_hex::<D::Error>(&shape.h)?).ok_or_else(|| {
+                D::Error::custom(format!("not a point of the curve: {}", shape.h))
+            })?;
+            Ok(EcPublicKey { h })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct EcPrivateKeyShape {
+        version: u32,
+        x: String,
+    }
+
+    /// x is the canonical encoding of the scalar.
+    impl<C: Curve> Serialize for EcPrivateKey<C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            EcPrivateKeyShape {
+                version: FORMAT_VERSION,
+                x: bytes_to_hex(&C::scalar_to_bytes(&self.x)),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, C: Curve> Deserialize<'de> for EcPrivateKey<C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let shape = EcPrivateKeyShape::deserialize(deserializer)?;
+            check_version(shape.version)?;
+            let x =
+                C::scalar_from_bytes(&bytes_from_hex::<D::Error>(&shape.x)?).ok_or_else(|| {
+                    D::Error::custom(format!("not a scalar of the curve: {}", shape.x))
+                })?;
+            Ok(EcPrivateKey { x })
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignatureShape {
+    version: u32,
+    r: String,
+    s: String,
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SignatureShape {
+            version: FORMAT_VERSION,
+            r: to_hex(&self.r),
+            s: to_hex(&self.s),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = SignatureShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        Ok(Signature {
+            r: from_hex(&shape.r)?,
+            s: from_hex(&shape.s)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReEncryptionKeyShape {
+    version: u32,
+    p: String,
+    g: String,
+    rk: String,
+}
+
+impl Serialize for ReEncryptionKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ReEncryptionKeyShape {
+            version: FORMAT_VERSION,
+            p: to_hex(&self.p),
+            g: to_hex(&self.g),
+            rk: to_hex(&self.rk),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReEncryptionKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = ReEncryptionKeyShape::deserialize(deserializer)?;
+        check_version(shape.version)?;
+        Ok(ReEncryptionKey {
+            p: from_hex(&shape.p)?,
+            g: from_hex(&shape.g)?,
+            rk: from_hex(&shape.rk)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::{Decryption, Encryption};
+    use crate::groups::StandardGroup;
+    use crate::{KeyFormat, RawKey};
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_public_key_json_shape() {
+        let pubkey = PublicKey::<BigInt>::from_hex_str("0x747c85d7, 0x747c85d6, 0xb2040843, 32");
+        let expected =
+            r#"{"version":1,"p":"0x747c85d7","g":"0x747c85d6","h":"0xb2040843","bit_length":32}"#;
+        assert_eq!(expected, serde_json::to_string(&pubkey).unwrap());
+        assert_eq!(
+            expected,
+            serde_json::to_string(&pubkey.clone().to_raw()).unwrap()
+        );
+        let restored: PublicKey<BigInt> = serde_json::from_str(expected).unwrap();
+        assert_eq!((&pubkey.group, &pubkey.h), (&restored.group, &restored.h));
+        let restored: RawPublicKey = serde_json::from_str(expected).unwrap();
+        assert_eq!(pubkey.to_raw(), restored);
+
+        let other_version = expected.replace(r#""version":1"#, r#""version":2"#);
+        assert!(serde_json::from_str::<PublicKey<BigInt>>(&other_version).is_err());
+        let decimal = expected.replace("0x747c85d7", "1954317783");
+        assert!(serde_json::from_str::<PublicKey<BigInt>>(&decimal).is_err());
+    }
+
+    #[test]
+    fn test_keys_and_ciphertext_round_trip() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let group = Arc::new(StandardGroup::Modp1536.params());
+        let (pubkey, private_key) = group.generate_key_pair(&mut rng);
+
+        let json = serde_json::to_string(&private_key).unwrap();
+        let private_key: PrivateKey<BigInt> = serde_json::from_str(&json).unwrap();
+        let json = serde_json::to_string(&*group).unwrap();
+        assert_eq!(*group, serde_json::from_str(&json).unwrap());
+        let raw_key = RawGroupPublicKey::new(&pubkey);
+        let json = serde_json::to_string(&raw_key).unwrap();
+        assert_eq!(
+            format!(
+                r#"{{"version":1,"group":"{}","h":"{}"}}"#,
+                bytes_to_hex(&group.id()),
+                to_hex(&pubkey.h)
+            ),
+            json
+        );
+        assert_eq!(raw_key, serde_json::from_str(&json).unwrap());
+
+        let cipher = String::from("serde message")
+            .encrypt(&pubkey, &mut rng)
+            .unwrap();
+        let parsed = Ciphertext::parse(&cipher).unwrap();
+        let json = serde_json::to_string(&parsed).unwrap();
+        let restored: Ciphertext = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, restored);
+        assert_eq!(
+            Some(String::from("serde message")),
+            restored.to_cipher_string().decrypt(&private_key)
+        );
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let (pubkey, private_key) = StandardGroup::Modp1536.generate_key_pair(&mut rng);
+        let envelope = crate::envelope::seal(&[&pubkey], b"serde payload", &mut rng).unwrap();
+        let json = serde_json::to_string(&envelope).unwrap();
+        let restored: Envelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope, restored);
+        assert_eq!(Ok(b"serde payload".to_vec()), restored.open(&private_key));
+
+        let short_id = json.replacen(r#""recipient":"0x"#, r#""recipient":"0x00"#, 1);
+        assert!(serde_json::from_str::<Envelope>(&short_id).is_err());
+    }
+
+    #[test]
+    fn test_capsule_round_trip() {
+        let (head, mut rng) = crate::generate_pub_key(&SEED, 32, 16);
+        let capsule = crate::timelock::TimeLock::new(head, 2, 32).seal(b"later", &mut rng);
+        let json = serde_json::to_string(&capsule).unwrap();
+        let restored: Capsule = serde_json::from_str(&json).unwrap();
+        assert_eq!(capsule, restored);
+        assert!(restored.verify_chain());
+    }
+
+    #[test]
+    fn test_cramer_shoup_signature_and_proxy_round_trip() {
+        let (cs_pubkey, cs_private_key, mut rng) =
+            crate::cramer_shoup::generate_key_pair_from_seed(&SEED, 64, 16);
+        let json = serde_json::to_string(&cs_pubkey).unwrap();
+        assert_eq!(cs_pubkey, serde_json::from_str(&json).unwrap());
+        let json = serde_json::to_string(&cs_private_key).unwrap();
+        let restored: CramerShoupPrivateKey<BigInt> = serde_json::from_str(&json).unwrap();
+        assert_eq!(cs_private_key, restored);
+        let cipher = String::from("cca").encrypt(&cs_pubkey, &mut rng).unwrap();
+        assert_eq!(Some(String::from("cca")), cipher.decrypt(&restored));
+
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (_, alice_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let (_, bob_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let signature =
+            crate::signature::sign(&alice_key, crate::signature::SignatureScheme::Dsa, b"m")
+                .unwrap();
+        let json = serde_json::to_string(&signature).unwrap();
+        assert_eq!(
+            format!(
+                r#"{{"version":1,"r":"{}","s":"{}"}}"#,
+                to_hex(&signature.r),
+                to_hex(&signature.s)
+            ),
+            json
+        );
+        assert_eq!(signature, serde_json::from_str(&json).unwrap());
+        let rk = ReEncryptionKey::new(&alice_key, &bob_key).unwrap();
+        let json = serde_json::to_string(&rk).unwrap();
+        assert_eq!(rk, serde_json::from_str(&json).unwrap());
+    }
+
+    /// Round trips EC keys on the curve C and rejects encodings off the curve.
+    #[cfg(any(feature = "ristretto", feature = "secp256k1"))]
+    fn check_ec_keys<C: crate::ec::Curve>() {
+        use crate::ec::{EcPrivateKey, EcPublicKey};
+        let (pubkey, private_key, _) = crate::ec::generate_key_pair_from_seed::<C>(&SEED);
+        let json = serde_json::to_string(&pubkey).unwrap();
+        assert_eq!(pubkey, serde_json::from_str(&json).unwrap());
+        let json = serde_json::to_string(&private_key).unwrap();
+        assert_eq!(private_key, serde_json::from_str(&json).unwrap());
+        let not_a_scalar = format!(r#"{{"version":1,"x":"0x{}"}}"#, "ff".repeat(32));
+        assert!(serde_json::from_str::<EcPrivateKey<C>>(&not_a_scalar).is_err());
+        let not_a_point = format!(r#"{{"version":1,"h":"0x{}"}}"#, "ff".repeat(32));
+        assert!(serde_json::from_str::<EcPublicKey<C>>(&not_a_point).is_err());
+    }
+
+    #[cfg(feature = "ristretto")]
+    #[test]
+    fn test_ristretto_keys_round_trip() {
+        check_ec_keys::<crate::ec::Ristretto>();
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_secp256k1_keys_round_trip() {
+        check_ec_keys::<crate::ec::Secp256k1>();
+    }
+}
@@ -0,0 +1,581 @@
+//! shuffle mod
+//! verifiable re-encryption shuffle of elgamal ciphertexts, the building block of a mix-net.
+//! Ciphertexts are permuted and re-randomized, and a Terelius-Wikström proof shows the
+//! output is a re-encryption of a permutation of the input without revealing which.
+//! The proof follows the pseudo-code of Haenni, Locher, Koenig and Dubuis,
+//! "Pseudo-Code Algorithms for Verifiable Re-Encryption Mix-Nets" (2017), extended to
+//! ciphertexts of several cipher pairs, and works in the quadratic residue subgroup.
+use crate::elgamal::{format_cipher_pairs, parse_cipher_pairs};
+use crate::generic::PublicKey;
+use crate::proofs::{challenge, is_subgroup_generator};
+use crate::utils;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+use rand_core::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Errors of shuffling a ciphertext list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShuffleError {
+    /// The ciphertext list is empty.
+    Empty,
+    /// A ciphertext does not parse or an element is outside the quadratic residue subgroup.
+    InvalidCiphertext(usize),
+    /// Ciphertexts have different numbers of cipher pairs.
+    WidthMismatch(usize),
+}
+
+/// A shuffled ciphertext list with the proof of correct shuffle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shuffle {
+    pub ciphertexts: Vec<String>,
+    pub proof: ShuffleProof,
+}
+
+/// Terelius-Wikström proof of a shuffle of N ciphertexts of w cipher pairs each.
+/// # Logic Desc
+/// ```text
+/// commitments: c[j] = g^r[j] * h[i] for output position i = psi^-1(j)
+/// chain: c_hat[i] = g^r_hat[i] * c_hat[i-1]^u'[i], c_hat[-1] = h[0]
+/// t = (t1, t2, t3, t4 for every pair column, t_hat) are the prover's commitments
+/// s = (s1, s2, s3, s4 for every pair column, s_hat, s_prime) are the responses to the challenge
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShuffleProof {
+    pub commitments: Vec<BigInt>,
+    pub chain: Vec<BigInt>,
+    pub t1: BigInt,
+    pub t2: BigInt,
+    pub t3: BigInt,
+    pub t4: Vec<(BigInt, BigInt)>,
+    pub t_hat: Vec<BigInt>,
+    pub s1: BigInt,
+    pub s2: BigInt,
+    pub s3: BigInt,
+    pub s4: Vec<BigInt>,
+    pub s_hat: Vec<BigInt>,
+    pub s_prime: Vec<BigInt>,
+}
+
+type Ciphertexts = Vec<Vec<(BigInt, BigInt)>>;
+
+/// The group the shuffle works in, with p = 2q + 1.
+struct Group<'a> {
+    key: &'a PublicKey<BigInt>,
+    q: BigInt,
+}
+
+impl<'a> Group<'a> {
+    fn new(key: &'a PublicKey<BigInt>) -> Self {
+        Group {
+            key,
+            q: (&key.group.p - 1u32) / 2u32,
+        }
+    }
+
+    fn p(&self) -> &BigInt {
+        &self.key.group.p
+    }
+
+    fn contains(&self, element: &BigInt) -> bool {
+        element > &BigInt::from(0)
+            && element < self.p()
+            && utils::is_quadratic_residue(element, self.p())
+    }
+
+    /// x^e mod p, with negative exponents taken in the order q subgroup.
+    fn pow(&self, x: &BigInt, e: &BigInt) -> BigInt {
+        x.modpow(&e.mod_floor(&self.q), self.p())
+    }
+
+    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b).mod_floor(self.p())
+    }
+
+    fn random_exponent<R: RngCore>(&self, rng: &mut R) -> BigInt {
+        utils::gen_bigint_range(rng, &BigInt::from(0), &(&self.q - 1u32))
+    }
+
+    /// product of bases[i]^exponents[i] mod p
+    fn multi_pow<'b, I>(&self, terms: I) -> BigInt
+    where
+        I: IntoIterator<Item = (&'b BigInt, &'b BigInt)>,
+    {
+        terms.into_iter().fold(BigInt::from(1), |acc, (x, e)| {
+            self.mul(&acc, &self.pow(x, e))
+        })
+    }
+
+    /// Independent generators h[0..=n] of the subgroup, hashed from p so nobody knows
+    /// their discrete logarithms to g or to each other.
+    fn generators(&self, n: usize) -> Vec<BigInt> {
+        let byte_len = (self.p().bits() as usize + 64).div_ceil(8);
+        (0..=n)
+            .map(|i| {
+                let mut counter = 0u64;
+                loop {
+                    let mut bytes = Vec::with_capacity(byte_len + 32);
+                    let mut block = 0u64;
+                    while bytes.len() < byte_len {
+                        let mut hasher = Sha256::new();
+                        hasher.update(b"elgamal_wasm/shuffle/generator");
+                        hasher.update(self.p().to_bytes_be().1);
+                        hasher.update((i as u64).to_le_bytes());
+                        hasher.update(counter.to_le_bytes());
+                        hasher.update(block.to_le_bytes());
+                        bytes.extend(hasher.finalize());
+                        block += 1;
+                    }
+                    let x =
+                        BigInt::from_bytes_be(Sign::Plus, &bytes[..byte_len]).mod_floor(self.p());
+                    let h = x.modpow(&BigInt::from(2), self.p());
+                    if h > BigInt::from(1) {
+                        return h;
+                    }
+                    counter += 1;
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parses ciphertexts of equal width with every element in the subgroup.
+fn parse_ciphertexts(group: &Group, ciphertexts: &[String]) -> Result<Ciphertexts, ShuffleError> {
+    if ciphertexts.is_empty() {
+        return Err(ShuffleError::Empty);
+    }
+    let mut parsed: Ciphertexts = Vec::with_capacity(ciphertexts.len());
+    for (idx, cipher) in ciphertexts.iter().enumerate() {
+        let cipher_pairs =
+            parse_cipher_pairs(cipher).ok_or(ShuffleError::InvalidCiphertext(idx))?;
+        if cipher_pairs
+            .iter()
+            .any(|(c, d)| !group.contains(c) || !group.contains(d))
+        {
+            return Err(ShuffleError::InvalidCiphertext(idx));
+        }
+        if idx > 0 && cipher_pairs.len() != parsed[0].len() {
+            return Err(ShuffleError::WidthMismatch(idx));
+        }
+        parsed.push(cipher_pairs);
+    }
+    Ok(parsed)
+}
+
+/// Challenges u[j] for every input ciphertext, hashed from the inputs, outputs and commitments.
+fn challenges(
+    group: &Group,
+    input: &Ciphertexts,
+    output: &Ciphertexts,
+    commitments: &[BigInt],
+) -> Vec<BigInt> {
+    let key = group.key;
+    let mut elements: Vec<&BigInt> = vec![&key.group.p, &key.group.g, &key.h];
+    for cipher_pairs in input.iter().chain(output.iter()) {
+        for (c, d) in cipher_pairs {
+            elements.push(c);
+            elements.push(d);
+        }
+    }
+    elements.extend(commitments.iter());
+    let seed = challenge(b"elgamal_wasm/shuffle/u", b"", &elements, &group.q);
+    (0..input.len())
+        .map(|j| {
+            challenge(
+                b"elgamal_wasm/shuffle/u",
+                &(j as u64).to_le_bytes(),
+                &[&seed],
+                &group.q,
+            )
+        })
+        .collect()
+}
+
+/// Challenge of the sigma protocol, hashed from the statement and the prover's commitments.
+fn proof_challenge(
+    group: &Group,
+    input: &Ciphertexts,
+    output: &Ciphertexts,
+    proof: &ShuffleProof,
+) -> BigInt {
+    let key = group.key;
+    let mut elements: Vec<&BigInt> = vec![&key.group.p, &key.group.g, &key.h];
+    for cipher_pairs in input.iter().chain(output.iter()) {
+        for (c, d) in cipher_pairs {
+            elements.push(c);
+            elements.push(d);
+        }
+    }
+    elements.extend(proof.commitments.iter());
+    elements.extend(proof.chain.iter());
+    elements.extend([&proof.t1, &proof.t2, &proof.t3]);
+    for (t41, t42) in &proof.t4 {
+        elements.push(t41);
+        elements.push(t42);
+    }
+    elements.extend(proof.t_hat.iter());
+    challenge(b"elgamal_wasm/shuffle/proof", b"", &elements, &group.q)
+}
+
+/// Permutes and re-encrypts the ciphertexts under `key`, and proves it.
+/// `key` must be a quadratic residue subgroup key, see `generate_qr_pub_key`, and every
+/// ciphertext must have the same number of cipher pairs.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::generic::Encryption;
+/// use elgamal::shuffle::shuffle;
+/// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 32, 32);
+/// let (pubkey, _) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let ballots: Vec<String> = ["yes", "no!", "yes"]
+///     .iter()
+///     .map(|vote| vote.to_string().encrypt(&pubkey, &mut rng).unwrap())
+///     .collect();
+/// let mixed = shuffle(&pubkey, &ballots, &mut rng).unwrap();
+/// assert!(mixed.proof.verify(&pubkey, &ballots, &mixed.ciphertexts));
+/// ```
+pub fn shuffle<R: RngCore>(
+    key: &PublicKey<BigInt>,
+    ciphertexts: &[String],
+    rng: &mut R,
+) -> Result<Shuffle, ShuffleError> {
+    let group = Group::new(key);
+    let input = parse_ciphertexts(&group, ciphertexts)?;
+    let n = input.len();
+    let width = input[0].len();
+    let q = &group.q;
+
+    // psi: output position i holds the re-encryption of input psi[i]
+    let mut psi: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let j = utils::gen_bigint_range(rng, &BigInt::from(0), &BigInt::from(i));
+        psi.swap(i, j.to_usize().unwrap());
+    }
+    // re-encryption randomness r'[j][k] of input j, pair k
+    let reenc: Vec<Vec<BigInt>> = (0..n)
+        .map(|_| (0..width).map(|_| group.random_exponent(rng)).collect())
+        .collect();
+    let output: Ciphertexts = psi
+        .iter()
+        .map(|&j| {
+            input[j]
+                .iter()
+                .zip(reenc[j].iter())
+                .map(|((c, d), r)| {
+                    (
+                        group.mul(c, &group.pow(&key.group.g, r)),
+                        group.mul(d, &group.pow(&key.h, r)),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    // permutation commitment
+    let generators = group.generators(n);
+    let (base, h) = (&generators[0], &generators[1..]);
+    let mut r = vec![BigInt::from(0); n];
+    let mut commitments = vec![BigInt::from(0); n];
+    for (i, &j) in psi.iter().enumerate() {
+        r[j] = group.random_exponent(rng);
+        commitments[j] = group.mul(&group.pow(&key.group.g, &r[j]), &h[i]);
+    }
+
+    let u = challenges(&group, &input, &output, &commitments);
+    let u_prime: Vec<BigInt> = psi.iter().map(|&j| u[j].clone()).collect();
+
+    // commitment chain
+    let r_hat: Vec<BigInt> = (0..n).map(|_| group.random_exponent(rng)).collect();
+    let mut chain = Vec::with_capacity(n);
+    for i in 0..n {
+        let previous = if i == 0 { base } else { &chain[i - 1] };
+        let c_hat = group.mul(
+            &group.pow(&key.group.g, &r_hat[i]),
+            &group.pow(previous, &u_prime[i]),
+        );
+        chain.push(c_hat);
+    }
+
+    // aggregated witnesses
+    let r_bar = r
+        .iter()
+        .fold(BigInt::from(0), |acc, x| (acc + x).mod_floor(q));
+    let mut v = vec![BigInt::from(1); n];
+    for i in (0..n - 1).rev() {
+        v[i] = (&u_prime[i + 1] * &v[i + 1]).mod_floor(q);
+    }
+    let r_hat_sum = r_hat
+        .iter()
+        .zip(v.iter())
+        .fold(BigInt::from(0), |acc, (x, v)| (acc + x * v).mod_floor(q));
+    let r_tilde = r
+        .iter()
+        .zip(u.iter())
+        .fold(BigInt::from(0), |acc, (x, u)| (acc + x * u).mod_floor(q));
+    let r_prime: Vec<BigInt> = (0..width)
+        .map(|k| {
+            reenc
+                .iter()
+                .zip(u.iter())
+                .fold(BigInt::from(0), |acc, (x, u)| {
+                    (acc + &x[k] * u).mod_floor(q)
+                })
+        })
+        .collect();
+
+    // prover's commitments
+    let w1 = group.random_exponent(rng);
+    let w2 = group.random_exponent(rng);
+    let w3 = group.random_exponent(rng);
+    let w4: Vec<BigInt> = (0..width).map(|_| group.random_exponent(rng)).collect();
+    let w_hat: Vec<BigInt> = (0..n).map(|_| group.random_exponent(rng)).collect();
+    let w_prime: Vec<BigInt> = (0..n).map(|_| group.random_exponent(rng)).collect();
+    let t1 = group.pow(&key.group.g, &w1);
+    let t2 = group.pow(&key.group.g, &w2);
+    let t3 = group.mul(
+        &group.pow(&key.group.g, &w3),
+        &group.multi_pow(h.iter().zip(w_prime.iter())),
+    );
+    let t4 = (0..width)
+        .map(|k| {
+            let neg_w4 = -&w4[k];
+            let t41 = group.mul(
+                &group.pow(&key.h, &neg_w4),
+                &group.multi_pow(output.iter().map(|e| &e[k].1).zip(w_prime.iter())),
+            );
+            let t42 = group.mul(
+                &group.pow(&key.group.g, &neg_w4),
+                &group.multi_pow(output.iter().map(|e| &e[k].0).zip(w_prime.iter())),
+            );
+            (t41, t42)
+        })
+        .collect();
+    let t_hat = (0..n)
+        .map(|i| {
+            let previous = if i == 0 { base } else { &chain[i - 1] };
+            group.mul(
+                &group.pow(&key.group.g, &w_hat[i]),
+                &group.pow(previous, &w_prime[i]),
+            )
+        })
+        .collect();
+    let mut proof = ShuffleProof {
+        commitments,
+        chain,
+        t1,
+        t2,
+        t3,
+        t4,
+        t_hat,
+        s1: BigInt::from(0),
+        s2: BigInt::from(0),
+        s3: BigInt::from(0),
+        s4: vec![],
+        s_hat: vec![],
+        s_prime: vec![],
+    };
+
+    // responses
+    let e = proof_challenge(&group, &input, &output, &proof);
+    let respond = |w: &BigInt, x: &BigInt| (w + &e * x).mod_floor(q);
+    proof.s1 = respond(&w1, &r_bar);
+    proof.s2 = respond(&w2, &r_hat_sum);
+    proof.s3 = respond(&w3, &r_tilde);
+    proof.s4 = w4
+        .iter()
+        .zip(r_prime.iter())
+        .map(|(w, x)| respond(w, x))
+        .collect();
+    proof.s_hat = w_hat
+        .iter()
+        .zip(r_hat.iter())
+        .map(|(w, x)| respond(w, x))
+        .collect();
+    proof.s_prime = w_prime
+        .iter()
+        .zip(u_prime.iter())
+        .map(|(w, x)| respond(w, x))
+        .collect();
+
+    Ok(Shuffle {
+        ciphertexts: output.iter().map(|e| format_cipher_pairs(e)).collect(),
+        proof,
+    })
+}
+
+impl ShuffleProof {
+    /// Verifies that `output` is a re-encryption of a permutation of `input` under `key`.
+    /// Rejects keys whose g does not generate the quadratic residue subgroup, e.g. g = 1.
+    /// # Logic Desc
+    /// ```text
+    /// c_bar = product c[j] / product h[i]        t1 = c_bar^-e * g^s1
+    /// c_hat = c_hat[N-1] / h[0]^(product u)     t2 = c_hat^-e * g^s2
+    /// c_tilde = product c[j]^u[j]               t3 = c_tilde^-e * g^s3 * product h[i]^s'[i]
+    /// a = product d[j]^u[j], b = product c[j]^u[j] for every pair column
+    /// t4 = (a^-e * h^-s4 * product d'[i]^s'[i], b^-e * g^-s4 * product c'[i]^s'[i])
+    /// t_hat[i] = c_hat[i]^-e * g^s_hat[i] * c_hat[i-1]^s'[i]
+    /// ```
+    pub fn verify(&self, key: &PublicKey<BigInt>, input: &[String], output: &[String]) -> bool {
+        let group = Group::new(key);
+        if !is_subgroup_generator(&key.group.g, &key.group.p, &key.group.q)
+            || !group.contains(&key.h)
+        {
+            return false;
+        }
+        let (input, output) = match (
+            parse_ciphertexts(&group, input),
+            parse_ciphertexts(&group, output),
+        ) {
+            (Ok(input), Ok(output)) => (input, output),
+            _ => return false,
+        };
+        let n = input.len();
+        let width = input[0].len();
+        if output.len() != n
+            || output[0].len() != width
+            || self.commitments.len() != n
+            || self.chain.len() != n
+            || self.t_hat.len() != n
+            || self.s_hat.len() != n
+            || self.s_prime.len() != n
+            || self.t4.len() != width
+            || self.s4.len() != width
+        {
+            return false;
+        }
+        if !self
+            .commitments
+            .iter()
+            .chain(self.chain.iter())
+            .all(|x| group.contains(x))
+        {
+            return false;
+        }
+        let q = &group.q;
+        let generators = group.generators(n);
+        let (base, h) = (&generators[0], &generators[1..]);
+        let u = challenges(&group, &input, &output, &self.commitments);
+        let e = proof_challenge(&group, &input, &output, self);
+        let neg_e = -&e;
+
+        let h_prod = h.iter().fold(BigInt::from(1), |acc, x| group.mul(&acc, x));
+        let c_bar = group.mul(
+            &self
+                .commitments
+                .iter()
+                .fold(BigInt::from(1), |acc, x| group.mul(&acc, x)),
+            &group.pow(&h_prod, &BigInt::from(-1)),
+        );
+        let u_prod = u
+            .iter()
+            .fold(BigInt::from(1), |acc, x| (acc * x).mod_floor(q));
+        let c_hat = group.mul(&self.chain[n - 1], &group.pow(base, &-u_prod));
+        let c_tilde = group.multi_pow(self.commitments.iter().zip(u.iter()));
+
+        let t1 = group.mul(&group.pow(&c_bar, &neg_e), &group.pow(&key.group.g, &self.s1));
+        let t2 = group.mul(&group.pow(&c_hat, &neg_e), &group.pow(&key.group.g, &self.s2));
+        let t3 = group.mul(
+            &group.mul(&group.pow(&c_tilde, &neg_e), &group.pow(&key.group.g, &self.s3)),
+            &group.multi_pow(h.iter().zip(self.s_prime.iter())),
+        );
+        if t1 != self.t1 || t2 != self.t2 || t3 != self.t3 {
+            return false;
+        }
+        for k in 0..width {
+            let a = group.multi_pow(input.iter().map(|e| &e[k].1).zip(u.iter()));
+            let b = group.multi_pow(input.iter().map(|e| &e[k].0).zip(u.iter()));
+            let neg_s4 = -&self.s4[k];
+            let t41 = group.mul(
+                &group.mul(&group.pow(&a, &neg_e), &group.pow(&key.h, &neg_s4)),
+                &group.multi_pow(output.iter().map(|e| &e[k].1).zip(self.s_prime.iter())),
+            );
+            let t42 = group.mul(
+                &group.mul(&group.pow(&b, &neg_e), &group.pow(&key.group.g, &neg_s4)),
+                &group.multi_pow(output.iter().map(|e| &e[k].0).zip(self.s_prime.iter())),
+            );
+            if (t41, t42) != self.t4[k] {
+                return false;
+            }
+        }
+        (0..n).all(|i| {
+            let previous = if i == 0 { base } else { &self.chain[i - 1] };
+            let t_hat = group.mul(
+                &group.mul(
+                    &group.pow(&self.chain[i], &neg_e),
+                    &group.pow(&key.group.g, &self.s_hat[i]),
+                ),
+                &group.pow(previous, &self.s_prime[i]),
+            );
+            t_hat == self.t_hat[i]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::{Decryption, Encryption};
+    use crate::groups::GroupParams;
+    use std::sync::Arc;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_shuffle_is_verifiable_permutation() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (pubkey, private_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+        // with 7 byte chunks every name takes two cipher pairs
+        let votes = ["alice", "bob", "carol", "dave", "erin", "frank"];
+        let ballots: Vec<String> = votes
+            .iter()
+            .map(|vote| vote.to_string().encrypt(&pubkey, &mut rng).unwrap())
+            .collect();
+        let mixed = shuffle(&pubkey, &ballots, &mut rng).unwrap();
+        assert!(mixed.proof.verify(&pubkey, &ballots, &mixed.ciphertexts));
+        let mut decrypted: Vec<String> = mixed
+            .ciphertexts
+            .iter()
+            .map(|cipher| cipher.decrypt(&private_key).unwrap())
+            .collect();
+        decrypted.sort();
+        let mut expected: Vec<String> = votes.iter().map(|vote| vote.to_string()).collect();
+        expected.sort();
+        assert_eq!(expected, decrypted);
+        // the proof does not verify against another input list
+        let mut other = ballots.clone();
+        other[0] = String::from("eve").encrypt(&pubkey, &mut rng).unwrap();
+        assert!(!mixed.proof.verify(&pubkey, &other, &mixed.ciphertexts));
+    }
+
+    #[test]
+    fn test_replaced_output_is_rejected() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (pubkey, _) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let ballots: Vec<String> = ["yes", "nay", "yes"]
+            .iter()
+            .map(|vote| vote.to_string().encrypt(&pubkey, &mut rng).unwrap())
+            .collect();
+        let mixed = shuffle(&pubkey, &ballots, &mut rng).unwrap();
+        let mut forged = mixed.ciphertexts.clone();
+        forged[1] = String::from("nay").encrypt(&pubkey, &mut rng).unwrap();
+        assert!(!mixed.proof.verify(&pubkey, &ballots, &forged));
+        assert_eq!(Err(ShuffleError::Empty), shuffle(&pubkey, &[], &mut rng));
+    }
+
+    #[test]
+    fn test_key_with_trivial_generator_is_rejected() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let (pubkey, _) = crate::generate_key_pair(&group_key.group, &mut rng);
+        let ballots: Vec<String> = ["yes", "nay"]
+            .iter()
+            .map(|vote| vote.to_string().encrypt(&pubkey, &mut rng).unwrap())
+            .collect();
+        // g = h = 1 makes re-encryption the identity, the proof would show nothing
+        let group = GroupParams::new(pubkey.group.p.clone(), BigInt::from(1), 64);
+        let weak_key = Arc::new(group).public_key(BigInt::from(1));
+        let mixed = shuffle(&weak_key, &ballots, &mut rng).unwrap();
+        assert!(!mixed.proof.verify(&weak_key, &ballots, &mixed.ciphertexts));
+    }
+}
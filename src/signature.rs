@@ -0,0 +1,275 @@
+//! signature mod
+//! elgamal signatures with the keys of this crate and deterministic nonces.
+//! `SignatureScheme::ElGamal` is the classic scheme with exponents mod p - 1,
+//! `SignatureScheme::Dsa` is DSA in the order q subgroup of quadratic residues.
+//! nonces follow RFC 6979 with HMAC-SHA-256, so signing the same message twice
+//! gives the same signature and no nonce is ever shared by two messages.
+use crate::generic::{PrivateKey, PublicKey};
+use crate::proofs::is_subgroup_generator;
+use crate::utils;
+use hmac::{Hmac, Mac};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signature schemes over a safe prime p = 2q + 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// r = g^k, s = (H(m) - x*r) / k mod p - 1, for any generator g.
+    ElGamal,
+    /// r = (g^k mod p) mod q, s = (H(m) + x*r) / k mod q, g must be a quadratic residue.
+    Dsa,
+}
+
+impl SignatureScheme {
+    /// Order of the exponents, p - 1 for ElGamal and q for DSA.
+    pub fn order(&self, p: &BigInt) -> BigInt {
+        match self {
+            SignatureScheme::ElGamal => p - 1u32,
+            SignatureScheme::Dsa => (p - 1u32) / 2u32,
+        }
+    }
+}
+
+/// Signature (r, s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: BigInt,
+    pub s: BigInt,
+}
+
+/// RFC 6979 section 3.2 candidate nonces in [1, order - 1] for the private exponent x
+/// and the message digest, in the order a signer tries them.
+#[derive(Debug, Clone)]
+pub struct NonceGenerator {
+    k: [u8; 32],
+    v: [u8; 32],
+    order: BigInt,
+    qlen: usize,
+    started: bool,
+}
+
+impl NonceGenerator {
+    pub fn new(x: &BigInt, order: &BigInt, digest: &[u8]) -> Self {
+        let qlen = order.bits() as usize;
+        let rolen = qlen.div_ceil(8);
+        let x = int_to_octets(x, rolen);
+        let h = int_to_octets(&bits_to_int(digest, qlen).mod_floor(order), rolen);
+        let mut nonces = NonceGenerator {
+            k: [0u8; 32],
+            v: [1u8; 32],
+            order: order.clone(),
+            qlen,
+            started: false,
+        };
+        for marker in [0u8, 1u8] {
+            nonces.k = nonces.hmac(&[&nonces.v, &[marker], &x, &h]);
+            nonces.v = nonces.hmac(&[&nonces.v]);
+        }
+        nonces
+    }
+
+    fn hmac(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut mac = HmacSha256::new_from_slice(&self.k).expect("HMAC takes keys of any size");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// K = HMAC_K(V || 0x00), V = HMAC_K(V), run after a rejected candidate.
+    fn reseed(&mut self) {
+        self.k = self.hmac(&[&self.v, &[0u8]]);
+        self.v = self.hmac(&[&self.v]);
+    }
+}
+
+impl Iterator for NonceGenerator {
+    type Item = BigInt;
+
+    fn next(&mut self) -> Option<BigInt> {
+        if self.started {
+            self.reseed();
+        }
+        self.started = true;
+        loop {
+            let mut t = Vec::with_capacity(self.qlen / 8 + 32);
+            while t.len() * 8 < self.qlen {
+                self.v = self.hmac(&[&self.v]);
+                t.extend_from_slice(&self.v);
+            }
+            let k = bits_to_int(&t, self.qlen);
+            if k >= BigInt::from(1) && k < self.order {
+                return Some(k);
+            }
+            self.reseed();
+        }
+    }
+}
+
+/// bits2int of RFC 6979, the big endian integer of the leftmost qlen bits.
+fn bits_to_int(bytes: &[u8], qlen: usize) -> BigInt {
+    let int = BigInt::from_bytes_be(Sign::Plus, bytes);
+    let blen = bytes.len() * 8;
+    if blen > qlen {
+        int >> (blen - qlen)
+    } else {
+        int
+    }
+}
+
+/// int2octets of RFC 6979, x as rolen big endian bytes.
+fn int_to_octets(x: &BigInt, rolen: usize) -> Vec<u8> {
+    let bytes = x.to_bytes_be().1;
+    let mut octets = vec![0u8; rolen.saturating_sub(bytes.len())];
+    octets.extend_from_slice(&bytes);
+    octets
+}
+
+/// SHA-256 digest of the message and its bits2int mod order.
+fn hash_message(message: &[u8], order: &BigInt) -> ([u8; 32], BigInt) {
+    let digest: [u8; 32] = Sha256::digest(message).into();
+    let e = bits_to_int(&digest, order.bits() as usize).mod_floor(order);
+    (digest, e)
+}
+
+/// Signs a message with a deterministic nonce.
+/// Returns None for `SignatureScheme::Dsa` if g is not a quadratic residue, e.g. for
+/// keys of `generate_pub_key`.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::signature::{sign, verify, SignatureScheme};
+/// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 64, 16);
+/// let (pubkey, private_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let signature = sign(&private_key, SignatureScheme::Dsa, b"message").unwrap();
+/// assert!(verify(&pubkey, SignatureScheme::Dsa, b"message", &signature));
+/// assert!(!verify(&pubkey, SignatureScheme::Dsa, b"other message", &signature));
+/// ```
+pub fn sign(
+    key: &PrivateKey<BigInt>,
+    scheme: SignatureScheme,
+    message: &[u8],
+) -> Option<Signature> {
+    let p = &key.group.p;
+    let order = scheme.order(p);
+    if scheme == SignatureScheme::Dsa && !is_subgroup_generator(&key.group.g, p, &order) {
+        return None;
+    }
+    let x = key.x.mod_floor(&order);
+    let (digest, e) = hash_message(message, &order);
+    let mut nonces = NonceGenerator::new(&x, &order, &digest);
+    loop {
+        let k = nonces.next()?;
+        let (r, s) = match scheme {
+            SignatureScheme::ElGamal => {
+                let gcd = k.extended_gcd(&order);
+                if gcd.gcd != BigInt::from(1) {
+                    continue;
+                }
+                let r = key.group.g.modpow(&k, p);
+                let s = ((&e - &x * &r) * gcd.x).mod_floor(&order);
+                (r, s)
+            }
+            SignatureScheme::Dsa => {
+                let r = key.group.g.modpow(&k, p).mod_floor(&order);
+                let s = (utils::mod_inverse(&k, &order) * (&e + &x * &r)).mod_floor(&order);
+                (r, s)
+            }
+        };
+        if r != BigInt::from(0) && s != BigInt::from(0) {
+            return Some(Signature { r, s });
+        }
+    }
+}
+
+/// Verifies a signature of the message.
+/// # Logic Desc
+/// ```text
+/// ElGamal: 0 < r < p, 0 < s < p - 1 and g^H(m) = h^r * r^s mod p
+/// Dsa: 0 < r, s < q, w = s^-1 mod q and r = (g^(H(m)*w) * h^(r*w) mod p) mod q
+/// ```
+pub fn verify(
+    key: &PublicKey<BigInt>,
+    scheme: SignatureScheme,
+    message: &[u8],
+    signature: &Signature,
+) -> bool {
+    let p = &key.group.p;
+    let order = scheme.order(p);
+    let Signature { r, s } = signature;
+    let zero = BigInt::from(0);
+    if s <= &zero || s >= &order {
+        return false;
+    }
+    let (_, e) = hash_message(message, &order);
+    match scheme {
+        SignatureScheme::ElGamal => {
+            if r <= &zero || r >= p {
+                return false;
+            }
+            key.group.g.modpow(&e, p) == (key.h.modpow(r, p) * r.modpow(s, p)) % p
+        }
+        SignatureScheme::Dsa => {
+            if r <= &zero || r >= &order || !is_subgroup_generator(&key.group.g, p, &order) {
+                return false;
+            }
+            let w = utils::mod_inverse(s, &order);
+            let u1 = (&e * &w).mod_floor(&order);
+            let u2 = (r * &w).mod_floor(&order);
+            let v = (key.group.g.modpow(&u1, p) * key.h.modpow(&u2, p)) % p;
+            &v.mod_floor(&order) == r
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_sign_verify() {
+        let (legacy, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        let (qr, _) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        for (scheme, group_key) in [
+            (SignatureScheme::ElGamal, &legacy),
+            (SignatureScheme::ElGamal, &qr),
+            (SignatureScheme::Dsa, &qr),
+        ] {
+            let (pubkey, private_key) = crate::generate_key_pair(&group_key.group, &mut rng);
+            let signature = sign(&private_key, scheme, b"message").unwrap();
+            assert_eq!(
+                Some(&signature),
+                sign(&private_key, scheme, b"message").as_ref()
+            );
+            assert!(verify(&pubkey, scheme, b"message", &signature));
+            assert!(!verify(&pubkey, scheme, b"massage", &signature));
+            let other = sign(&private_key, scheme, b"massage").unwrap();
+            assert_ne!(signature.r, other.r);
+            let mut forged = signature.clone();
+            forged.s += 1u32;
+            assert!(!verify(&pubkey, scheme, b"message", &forged));
+        }
+        // DSA needs g of order q
+        let (_, private_key) = crate::generate_key_pair(&legacy.group, &mut rng);
+        assert_eq!(None, sign(&private_key, SignatureScheme::Dsa, b"message"));
+    }
+
+    #[test]
+    fn test_nonces_are_in_range_and_distinct() {
+        // an order just above a power of two makes most candidates out of range
+        let order = (BigInt::from(1) << 64) + 1u32;
+        let nonces: Vec<BigInt> = NonceGenerator::new(&BigInt::from(7), &order, &[0u8; 32])
+            .take(8)
+            .collect();
+        for (i, k) in nonces.iter().enumerate() {
+            assert!(k >= &BigInt::from(1) && k < &order);
+            assert!(!nonces[..i].contains(k));
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! stream mod
+//! streaming encryption of byte streams of any size, one chunk at a time.
+//! bytes are chunked like `Encryption`: k = (bit_length - 2) / 8 bytes per integer,
+//! ended by a 0x80 marker and zero padding in the last chunk, so encrypting
+//! BOM || UTF_16LE(text) gives the same ciphertext as `Encryption::encrypt`.
+//! the iterator types only use `core` and `alloc`, the `Read`/`Write` adapters need `std`.
+//! like the ciphertext of `Encryption` the stream is not authenticated: pairs can be
+//! reordered, replayed or dropped without notice, including whole chunks at the end
+//! when the new last chunk happens to end in 0x80 and zeros. use `envelope` or
+//! `cramer_shoup` where the stream must not be changed.
+use crate::elgamal::{
+    chunk_size, decode_from_group, encode_to_group, encrypt_codes, unblind, PAD_MARKER,
+};
+use crate::generic::{PrivateKey, PublicKey};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use num_bigint::{BigInt, Sign};
+use rand_core::RngCore;
+
+/// Error for stream encryption and decryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError {
+    /// The key cannot encrypt messages, see `GroupParams::encodes_messages`.
+    UnsupportedKey,
+    /// A cipher pair is malformed or does not decrypt to a chunk under this key.
+    InvalidCiphertext,
+    /// The stream ended without the padding of the last chunk, e.g. the padded chunk was
+    /// cut off. Dropping whole chunks is not always detected, see the module docs.
+    InvalidPadding,
+}
+
+/// Type alias for stream results.
+pub type StreamResult<T> = core::result::Result<T, StreamError>;
+
+/// Encrypts a byte stream chunk by chunk, buffering less than one chunk.
+#[derive(Debug, Clone)]
+pub struct StreamEncryptor<'a> {
+    key: &'a PublicKey<BigInt>,
+    k: usize,
+    buffer: Vec<u8>,
+}
+
+impl<'a> StreamEncryptor<'a> {
+    pub fn new(key: &'a PublicKey<BigInt>) -> StreamResult<Self> {
+        if !key.group.encodes_messages() {
+            return Err(StreamError::UnsupportedKey);
+        }
+        let k = chunk_size(key.group.bit_length);
+        Ok(StreamEncryptor {
+            key,
+            k,
+            buffer: Vec::with_capacity(k),
+        })
+    }
+
+    /// Encrypts every complete chunk of the buffered and new bytes, keeps the rest buffered.
+    pub fn update<R: RngCore>(&mut self, bytes: &[u8], rng: &mut R) -> Vec<(BigInt, BigInt)> {
+        let mut codes = Vec::new();
+        for byte in bytes {
+            self.buffer.push(*byte);
+            if self.buffer.len() == self.k {
+                codes.push(self.encode_buffer());
+            }
+        }
+        self.encrypt(&codes, rng)
+    }
+
+    /// Pads and encrypts the last chunk.
+    pub fn finish<R: RngCore>(mut self, rng: &mut R) -> (BigInt, BigInt) {
+        self.buffer.push(PAD_MARKER);
+        self.buffer.resize(self.k, 0);
+        let code = self.encode_buffer();
+        self.encrypt(&[code], rng).remove(0)
+    }
+
+    fn encode_buffer(&mut self) -> BigInt {
+        let chunk = BigInt::from_bytes_le(Sign::Plus, &self.buffer);
+        self.buffer.clear();
+        encode_to_group(&chunk, &self.key.group.p)
+    }
+
+    fn encrypt<R: RngCore>(&self, codes: &[BigInt], rng: &mut R) -> Vec<(BigInt, BigInt)> {
+        let (g, h, p) = (&self.key.group.g, &self.key.h, &self.key.group.p);
+        encrypt_codes(codes, p, rng, |y| (g.modpow(y, p), h.modpow(y, p)))
+    }
+}
+
+/// Decrypts a stream of cipher pairs, holding back one chunk to strip the padding at the end.
+#[derive(Debug, Clone)]
+pub struct StreamDecryptor<'a> {
+    key: &'a PrivateKey<BigInt>,
+    k: usize,
+    pending: Option<Vec<u8>>,
+}
+
+impl<'a> StreamDecryptor<'a> {
+    pub fn new(key: &'a PrivateKey<BigInt>) -> Self {
+        StreamDecryptor {
+            key,
+            k: chunk_size(key.group.bit_length),
+            pending: None,
+        }
+    }
+
+    /// Decrypts one pair and returns the bytes of the chunk before it.
+    pub fn update(&mut self, pair: &(BigInt, BigInt)) -> StreamResult<Vec<u8>> {
+        let chunk = self.decrypt_pair(pair)?;
+        Ok(self.pending.replace(chunk).unwrap_or_default())
+    }
+
+    /// Returns the bytes of the last chunk with the padding stripped.
+    /// Only checks that the last chunk is padded, not that it is the last chunk sent.
+    pub fn finish(self) -> StreamResult<Vec<u8>> {
+        let mut last = self.pending.ok_or(StreamError::InvalidPadding)?;
+        while last.last() == Some(&0) {
+            last.pop();
+        }
+        match last.pop() {
+            Some(PAD_MARKER) => Ok(last),
+            _ => Err(StreamError::InvalidPadding),
+        }
+    }
+
+    fn decrypt_pair(&self, (c, d): &(BigInt, BigInt)) -> StreamResult<Vec<u8>> {
+        let p = &self.key.group.p;
+        let s = c.modpow(&self.key.x, p);
+        let chunk =
+            decode_from_group(&unblind(d, &s, p), p).ok_or(StreamError::InvalidCiphertext)?;
+        let mut bytes = chunk.to_bytes_le().1;
+        if bytes.len() > self.k {
+            return Err(StreamError::InvalidCiphertext);
+        }
+        bytes.resize(self.k, 0);
+        Ok(bytes)
+    }
+}
+
+/// Iterator of the cipher pairs of a byte iterator.
+pub struct EncryptIter<'a, I, R> {
+    encryptor: Option<StreamEncryptor<'a>>,
+    bytes: I,
+    rng: &'a mut R,
+}
+
+/// Encrypts an iterator of bytes lazily, one cipher pair per chunk.
+/// Fails if the key cannot encrypt messages.
+///
+/// # Example
+///```rust
+/// use elgamal_wasm as elgamal;
+/// use elgamal::stream::{decrypt_iter, encrypt_iter};
+/// let (group_key, mut rng) = elgamal::generate_qr_pub_key(&[2929], 64, 16);
+/// let (pubkey, private_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+/// let data = vec![7u8; 100];
+/// let pairs: Vec<_> = encrypt_iter(&pubkey, data.iter().copied(), &mut rng)
+///     .unwrap()
+///     .collect();
+/// let plain: Result<Vec<u8>, _> = decrypt_iter(&private_key, pairs.into_iter()).collect();
+/// assert_eq!(Ok(data), plain);
+/// ```
+pub fn encrypt_iter<'a, I, R>(
+    key: &'a PublicKey<BigInt>,
+    bytes: I,
+    rng: &'a mut R,
+) -> StreamResult<EncryptIter<'a, I, R>>
+where
+    I: Iterator<Item = u8>,
+    R: RngCore,
+{
+    Ok(EncryptIter {
+        encryptor: Some(StreamEncryptor::new(key)?),
+        bytes,
+        rng,
+    })
+}
+
+impl<'a, I, R> Iterator for EncryptIter<'a, I, R>
+where
+    I: Iterator<Item = u8>,
+    R: RngCore,
+{
+    type Item = (BigInt, BigInt);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let encryptor = self.encryptor.as_mut()?;
+        for byte in self.bytes.by_ref() {
+            if let Some(pair) = encryptor.update(&[byte], self.rng).pop() {
+                return Some(pair);
+            }
+        }
+        Some(self.encryptor.take()?.finish(self.rng))
+    }
+}
+
+/// Iterator of the plaintext bytes of an iterator of cipher pairs.
+pub struct DecryptIter<'a, I> {
+    decryptor: Option<StreamDecryptor<'a>>,
+    pairs: I,
+    output: VecDeque<u8>,
+}
+
+/// Decrypts an iterator of cipher pairs lazily, yielding an error and stopping at a bad pair.
+pub fn decrypt_iter<I>(key: &PrivateKey<BigInt>, pairs: I) -> DecryptIter<'_, I>
+where
+    I: Iterator<Item = (BigInt, BigInt)>,
+{
+    DecryptIter {
+        decryptor: Some(StreamDecryptor::new(key)),
+        pairs,
+        output: VecDeque::new(),
+    }
+}
+
+impl<'a, I> Iterator for DecryptIter<'a, I>
+where
+    I: Iterator<Item = (BigInt, BigInt)>,
+{
+    type Item = StreamResult<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.output.is_empty() {
+            let decryptor = self.decryptor.as_mut()?;
+            let bytes = match self.pairs.next() {
+                Some(pair) => decryptor.update(&pair),
+                None => self.decryptor.take()?.finish(),
+            };
+            match bytes {
+                Ok(bytes) => self.output.extend(bytes),
+                Err(err) => {
+                    self.decryptor = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.output.pop_front().map(Ok)
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::io::{DecryptReader, EncryptWriter};
+
+#[cfg(feature = "std")]
+mod io {
+    use super::*;
+    use crate::elgamal::STR_RADIX;
+    use num_traits::Num;
+    use std::io::{BufRead, Error, ErrorKind, Read, Result, Write};
+
+    /// Parses a whitespace separated cipher integer.
+    fn parse_cipher_int(token: &[u8]) -> StreamResult<BigInt> {
+        let token = std::str::from_utf8(token).map_err(|_| StreamError::InvalidCiphertext)?;
+        BigInt::from_str_radix(token, STR_RADIX).map_err(|_| StreamError::InvalidCiphertext)
+    }
+
+    impl From<StreamError> for Error {
+        fn from(err: StreamError) -> Self {
+            Error::new(ErrorKind::InvalidData, format!("{:?}", err))
+        }
+    }
+
+    /// Encrypts everything written to it and writes the ciphertext in the "c d c d " format.
+    /// `finish` must be called to write the last chunk.
+    pub struct EncryptWriter<'a, W: Write, R: RngCore> {
+        encryptor: StreamEncryptor<'a>,
+        inner: W,
+        rng: R,
+    }
+
+    impl<'a, W: Write, R: RngCore> EncryptWriter<'a, W, R> {
+        pub fn new(key: &'a PublicKey<BigInt>, inner: W, rng: R) -> StreamResult<Self> {
+            Ok(EncryptWriter {
+                encryptor: StreamEncryptor::new(key)?,
+                inner,
+                rng,
+            })
+        }
+
+        /// Writes the padded last chunk and returns the inner writer.
+        pub fn finish(mut self) -> Result<W> {
+            let pair = self.encryptor.finish(&mut self.rng);
+            write_pair(&mut self.inner, &pair)?;
+            self.inner.flush()?;
+            Ok(self.inner)
+        }
+    }
+
+    fn write_pair<W: Write>(inner: &mut W, (c, d): &(BigInt, BigInt)) -> Result<()> {
+        write!(
+            inner,
+            "{} {} ",
+            c.to_str_radix(STR_RADIX),
+            d.to_str_radix(STR_RADIX)
+        )
+    }
+
+    impl<'a, W: Write, R: RngCore> Write for EncryptWriter<'a, W, R> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            for pair in self.encryptor.update(buf, &mut self.rng) {
+                write_pair(&mut self.inner, &pair)?;
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    /// Reads a ciphertext in the "c d c d " format and yields the plaintext bytes.
+    pub struct DecryptReader<'a, R: BufRead> {
+        decryptor: Option<StreamDecryptor<'a>>,
+        inner: R,
+        output: VecDeque<u8>,
+    }
+
+    impl<'a, R: BufRead> DecryptReader<'a, R> {
+        pub fn new(key: &'a PrivateKey<BigInt>, inner: R) -> Self {
+            DecryptReader {
+                decryptor: Some(StreamDecryptor::new(key)),
+                inner,
+                output: VecDeque::new(),
+            }
+        }
+
+        /// Next whitespace separated token, None at the end of the input.
+        fn next_token(&mut self) -> Result<Option<Vec<u8>>> {
+            let mut token = Vec::new();
+            loop {
+                let buf = self.inner.fill_buf()?;
+                if buf.is_empty() {
+                    break;
+                }
+                let mut used = 0;
+                let mut ended = false;
+                for byte in buf {
+                    used += 1;
+                    if byte.is_ascii_whitespace() {
+                        if !token.is_empty() {
+                            ended = true;
+                            break;
+                        }
+                    } else {
+                        token.push(*byte);
+                    }
+                }
+                self.inner.consume(used);
+                if ended {
+                    break;
+                }
+            }
+            Ok(if token.is_empty() { None } else { Some(token) })
+        }
+    }
+
+    impl<'a, R: BufRead> Read for DecryptReader<'a, R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            while self.output.is_empty() && self.decryptor.is_some() {
+                let bytes = match self.next_token()? {
+                    Some(c) => {
+                        let d = self.next_token()?.ok_or(StreamError::InvalidCiphertext)?;
+                        let pair = (parse_cipher_int(&c)?, parse_cipher_int(&d)?);
+                        self.decryptor.as_mut().unwrap().update(&pair)?
+                    }
+                    None => self.decryptor.take().unwrap().finish()?,
+                };
+                self.output.extend(bytes);
+            }
+            let n = buf.len().min(self.output.len());
+            for (slot, byte) in buf.iter_mut().zip(self.output.drain(..n)) {
+                *slot = byte;
+            }
+            Ok(n)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::elgamal::BOM;
+    #[cfg(feature = "std")]
+    use crate::generic::Encryption;
+    #[cfg(feature = "std")]
+    use encoding::all::UTF_16LE;
+    #[cfg(feature = "std")]
+    use encoding::{EncoderTrap, Encoding};
+    #[cfg(feature = "std")]
+    use std::io::{Read, Write};
+
+    const SEED: [u32; 1] = [2929];
+
+    fn key_pair() -> (PublicKey<BigInt>, PrivateKey<BigInt>) {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        crate::generate_key_pair(&group_key.group, &mut rng)
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_stream_matches_encrypt() {
+        let (pubkey, _) = crate::generate_pub_key(&SEED, 64, 16);
+        let msg = String::from("streamed message, 中文");
+        let mut bytes = BOM.to_vec();
+        bytes.extend(UTF_16LE.encode(&msg, EncoderTrap::Strict).unwrap());
+        let mut rng_a = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let mut rng_b = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let mut writer = EncryptWriter::new(&pubkey, Vec::new(), &mut rng_b).unwrap();
+        writer.write_all(&bytes).unwrap();
+        let cipher = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(msg.encrypt(&pubkey, &mut rng_a), Some(cipher));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_writer_reader_round_trip() {
+        let (pubkey, private_key) = key_pair();
+        let k = chunk_size(pubkey.group.bit_length);
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        for len in [0, 1, k - 1, k, k + 1, 3 * k, 3 * k + 2, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+            let mut writer = EncryptWriter::new(&pubkey, Vec::new(), &mut rng).unwrap();
+            // odd sized writes across chunk boundaries
+            for piece in data.chunks(5) {
+                writer.write_all(piece).unwrap();
+            }
+            let cipher = writer.finish().unwrap();
+            let mut plain = Vec::new();
+            DecryptReader::new(&private_key, cipher.as_slice())
+                .read_to_end(&mut plain)
+                .unwrap();
+            assert_eq!(data, plain);
+        }
+    }
+
+    #[test]
+    fn test_truncated_stream_is_rejected() {
+        let (pubkey, private_key) = key_pair();
+        let mut rng = mt19937::MT19937::new_with_slice_seed(&SEED);
+        let data = [1u8; 40];
+        let mut pairs: Vec<_> = encrypt_iter(&pubkey, data.iter().copied(), &mut rng)
+            .unwrap()
+            .collect();
+        pairs.pop();
+        let plain: StreamResult<Vec<u8>> = decrypt_iter(&private_key, pairs.into_iter()).collect();
+        assert_eq!(Err(StreamError::InvalidPadding), plain);
+        let plain: StreamResult<Vec<u8>> =
+            decrypt_iter(&private_key, core::iter::empty()).collect();
+        assert_eq!(Err(StreamError::InvalidPadding), plain);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_truncated_reader_is_rejected() {
+        let (_, private_key) = key_pair();
+        let mut reader = DecryptReader::new(&private_key, "12 34 56".as_bytes());
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,589 @@
+//! threshold mod
+//! t-of-n threshold elgamal over the quadratic residue subgroup of a safe prime.
+//! The private key x is never assembled: a Pedersen (joint Feldman) distributed key
+//! generation gives each of n parties a share x_i = F(i) of a degree t - 1 polynomial
+//! F with F(0) = x, any t parties publish decryption shares c^(x_i) with
+//! Chaum-Pedersen proofs, and Lagrange interpolation in the exponent recovers c^x.
+use crate::elgamal::{decode_message, parse_cipher_pairs, unblind};
+use crate::generic::{PrivateKey, PublicKey};
+use crate::groups::GroupParams;
+use crate::proofs::{in_group, is_subgroup_generator, ChaumPedersenProof, DleqStatement};
+use crate::utils;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use rand_core::RngCore;
+use std::sync::Arc;
+
+/// Errors of the distributed key generation and threshold decryption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThresholdError {
+    /// Threshold is 0 or larger than the number of parties, g does not generate the
+    /// quadratic residue subgroup, or a dealer appears twice.
+    InvalidParams,
+    /// Party index is outside 1..=parties.
+    InvalidIndex(usize),
+    /// A dealer's commitments or share failed verification, the dealer should be disqualified.
+    InvalidDealing { dealer: usize },
+    /// Fewer than threshold valid decryption shares.
+    NotEnoughShares,
+    /// The ciphertext or the recovered plaintext is malformed.
+    InvalidCiphertext,
+}
+
+/// Type alias for threshold results.
+pub type ThresholdResult<T> = std::result::Result<T, ThresholdError>;
+
+/// Any `threshold` of `parties` can decrypt together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThresholdParams {
+    pub threshold: usize,
+    pub parties: usize,
+}
+
+impl ThresholdParams {
+    fn check(&self) -> ThresholdResult<()> {
+        if self.threshold == 0 || self.threshold > self.parties {
+            return Err(ThresholdError::InvalidParams);
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> ThresholdResult<()> {
+        if index == 0 || index > self.parties {
+            return Err(ThresholdError::InvalidIndex(index));
+        }
+        Ok(())
+    }
+}
+
+/// What a dealer sends to one recipient: its public commitments and the recipient's share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dealing {
+    pub dealer: usize,
+    pub commitments: Vec<BigInt>,
+    pub share: BigInt,
+}
+
+impl Dealing {
+    /// Feldman verification of the share against the dealer's commitments.
+    /// # Logic Desc
+    /// ```text
+    /// C[k] = g^a[k] mod p for the dealer's polynomial f(z) = sum(a[k] * z^k)
+    /// share = f(j) is valid if g^share = product of C[k]^(j^k) mod p
+    /// every C[k] must be a quadratic residue in [1, p - 1]
+    /// ```
+    pub fn verify(&self, group: &GroupParams, params: &ThresholdParams, recipient: usize) -> bool {
+        if self.commitments.len() != params.threshold
+            || params.check_index(self.dealer).is_err()
+            || params.check_index(recipient).is_err()
+        {
+            return false;
+        }
+        if self.share < BigInt::from(0) || self.share >= group.q {
+            return false;
+        }
+        if !valid_commitments(group, &self.commitments) {
+            return false;
+        }
+        group.g.modpow(&self.share, &group.p)
+            == eval_commitments(group, &self.commitments, recipient)
+    }
+}
+
+/// One party of the distributed key generation, holding its secret polynomial.
+#[derive(Debug, Clone)]
+pub struct DkgParticipant {
+    index: usize,
+    params: ThresholdParams,
+    group: Arc<GroupParams>,
+    coefficients: Vec<BigInt>,
+    commitments: Vec<BigInt>,
+}
+
+impl DkgParticipant {
+    /// Starts the key generation for party `index` in 1..=parties.
+    /// g of the group must generate the quadratic residue subgroup, see `generate_qr_pub_key`.
+    pub fn new<R: RngCore>(
+        index: usize,
+        params: ThresholdParams,
+        group: &Arc<GroupParams>,
+        rng: &mut R,
+    ) -> ThresholdResult<Self> {
+        params.check()?;
+        params.check_index(index)?;
+        check_group(group)?;
+        let coefficients: Vec<BigInt> = (0..params.threshold)
+            .map(|_| utils::gen_bigint_range(rng, &BigInt::from(0), &(&group.q - 1u32)))
+            .collect();
+        let commitments = coefficients
+            .iter()
+            .map(|a| group.g.modpow(a, &group.p))
+            .collect();
+        Ok(DkgParticipant {
+            index,
+            params,
+            group: group.clone(),
+            coefficients,
+            commitments,
+        })
+    }
+
+    /// Index of this party.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Feldman commitments to the coefficients, broadcast to every party.
+    pub fn commitments(&self) -> &[BigInt] {
+        &self.commitments
+    }
+
+    /// The dealing sent privately to party `recipient`, holding f(recipient) mod q.
+    pub fn dealing_for(&self, recipient: usize) -> ThresholdResult<Dealing> {
+        self.params.check_index(recipient)?;
+        let q = &self.group.q;
+        let j = BigInt::from(recipient);
+        // Horner's rule from the highest coefficient
+        let share = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(BigInt::from(0), |acc, a| (acc * &j + a).mod_floor(q));
+        Ok(Dealing {
+            dealer: self.index,
+            commitments: self.commitments.clone(),
+            share,
+        })
+    }
+
+    /// Verifies the dealings of the qualified dealers (this party's own included) and sums them
+    /// into this party's key share. All honest parties must use the same set of dealers,
+    /// each dealer at most once.
+    pub fn finalize(&self, dealings: &[Dealing]) -> ThresholdResult<KeyShare> {
+        if dealings.is_empty() {
+            return Err(ThresholdError::InvalidParams);
+        }
+        let q = &self.group.q;
+        let mut x = BigInt::from(0);
+        let mut dealers = Vec::with_capacity(dealings.len());
+        for dealing in dealings {
+            if dealers.contains(&dealing.dealer) {
+                return Err(ThresholdError::InvalidParams);
+            }
+            dealers.push(dealing.dealer);
+            if !dealing.verify(&self.group, &self.params, self.index) {
+                return Err(ThresholdError::InvalidDealing {
+                    dealer: dealing.dealer,
+                });
+            }
+            x = (x + &dealing.share).mod_floor(q);
+        }
+        Ok(KeyShare {
+            index: self.index,
+            key: self.group.private_key(x),
+        })
+    }
+}
+
+/// The joint public key and the verification key g^(x_i) of every party.
+#[derive(Debug, Clone)]
+pub struct ThresholdPublicKey {
+    pub key: PublicKey<BigInt>,
+    pub params: ThresholdParams,
+    pub verification_keys: Vec<BigInt>,
+}
+
+impl ThresholdPublicKey {
+    /// Computes the joint key from the commitments of the qualified dealers, anyone can do this.
+    /// `commitments` holds one (dealer index, commitments) entry per distinct dealer.
+    /// # Logic Desc
+    /// ```text
+    /// h = product of C_i[0] over dealers i
+    /// h_j = g^(x_j) = product of C_i[k]^(j^k) over dealers i and k
+    /// ```
+    pub fn from_commitments(
+        group: &Arc<GroupParams>,
+        params: ThresholdParams,
+        commitments: &[(usize, Vec<BigInt>)],
+    ) -> ThresholdResult<Self> {
+        params.check()?;
+        check_group(group)?;
+        if commitments.is_empty() || commitments.iter().any(|(_, c)| c.len() != params.threshold) {
+            return Err(ThresholdError::InvalidParams);
+        }
+        for (i, (dealer, dealer_commitments)) in commitments.iter().enumerate() {
+            params.check_index(*dealer)?;
+            if !valid_commitments(group, dealer_commitments) {
+                return Err(ThresholdError::InvalidDealing { dealer: *dealer });
+            }
+            if commitments[..i].iter().any(|(other, _)| other == dealer) {
+                return Err(ThresholdError::InvalidParams);
+            }
+        }
+        let p = &group.p;
+        let h = commitments
+            .iter()
+            .fold(BigInt::from(1), |acc, (_, c)| (acc * &c[0]).mod_floor(p));
+        let verification_keys = (1..=params.parties)
+            .map(|j| {
+                commitments.iter().fold(BigInt::from(1), |acc, (_, c)| {
+                    (acc * eval_commitments(group, c, j)).mod_floor(p)
+                })
+            })
+            .collect();
+        Ok(ThresholdPublicKey {
+            key: group.public_key(h),
+            params,
+            verification_keys,
+        })
+    }
+
+    /// Checks the proofs of a decryption share against the party's verification key.
+    pub fn verify_share(&self, cipher: &str, share: &DecryptionShare) -> bool {
+        match parse_cipher_pairs(cipher) {
+            Some(cipher_pairs) => self.verify_share_pairs(&cipher_pairs, share),
+            None => false,
+        }
+    }
+
+    fn verify_share_pairs(
+        &self,
+        cipher_pairs: &[(BigInt, BigInt)],
+        share: &DecryptionShare,
+    ) -> bool {
+        if self.params.check_index(share.index).is_err()
+            || share.shares.len() != cipher_pairs.len()
+            || share.proofs.len() != cipher_pairs.len()
+        {
+            return false;
+        }
+        let group = &self.key.group;
+        let h_i = &self.verification_keys[share.index - 1];
+        let context = share_context(share.index);
+        cipher_pairs
+            .iter()
+            .zip(share.shares.iter().zip(share.proofs.iter()))
+            .all(|((c, _), (s, proof))| {
+                let statement = DleqStatement {
+                    p: &group.p,
+                    order: &group.q,
+                    g: &group.g,
+                    h: h_i,
+                    c,
+                    s,
+                };
+                proof.verify(&statement, &context)
+            })
+    }
+
+    /// Verifies the decryption shares and combines the first `threshold` valid ones
+    /// with distinct indices to recover the plaintext.
+    /// # Logic Desc
+    /// ```text
+    /// l_i = product over j in S, j != i of j / (j - i) mod q
+    /// s = c^x = product of s_i^(l_i) mod p
+    /// m = d * s^-1 mod p
+    /// ```
+    pub fn combine(&self, cipher: &str, shares: &[DecryptionShare]) -> ThresholdResult<String> {
+        let cipher_pairs = parse_cipher_pairs(cipher).ok_or(ThresholdError::InvalidCiphertext)?;
+        let mut selected: Vec<&DecryptionShare> = Vec::with_capacity(self.params.threshold);
+        for share in shares {
+            if selected.len() == self.params.threshold {
+                break;
+            }
+            if selected.iter().all(|s| s.index != share.index)
+                && self.verify_share_pairs(&cipher_pairs, share)
+            {
+                selected.push(share);
+            }
+        }
+        if selected.len() < self.params.threshold {
+            return Err(ThresholdError::NotEnoughShares);
+        }
+        let group = &self.key.group;
+        let indices: Vec<usize> = selected.iter().map(|s| s.index).collect();
+        let lambdas: Vec<BigInt> = indices
+            .iter()
+            .map(|&i| lagrange_coefficient(i, &indices, &group.q))
+            .collect();
+        let p = &group.p;
+        let mut plain_text = Vec::with_capacity(cipher_pairs.len());
+        for (idx, (_, d)) in cipher_pairs.iter().enumerate() {
+            let s = selected
+                .iter()
+                .zip(lambdas.iter())
+                .fold(BigInt::from(1), |acc, (share, lambda)| {
+                    (acc * share.shares[idx].modpow(lambda, p)).mod_floor(p)
+                });
+            plain_text.push(unblind(d, &s, p));
+        }
+        decode_message(&plain_text, p, group.bit_length).ok_or(ThresholdError::InvalidCiphertext)
+    }
+}
+
+/// A party's share x_i of the private key, stored as a private key with x = x_i.
+#[derive(Debug, Clone)]
+pub struct KeyShare {
+    pub index: usize,
+    pub key: PrivateKey<BigInt>,
+}
+
+/// A party's partial decryption s_i = c^(x_i) of every cipher pair with correctness proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptionShare {
+    pub index: usize,
+    pub shares: Vec<BigInt>,
+    pub proofs: Vec<ChaumPedersenProof>,
+}
+
+impl KeyShare {
+    /// Computes this party's decryption share of a ciphertext printed by `Encryption`.
+    pub fn partial_decrypt<R: RngCore>(
+        &self,
+        cipher: &str,
+        rng: &mut R,
+    ) -> Option<DecryptionShare> {
+        let cipher_pairs = parse_cipher_pairs(cipher)?;
+        let key = &self.key;
+        let group = &key.group;
+        let h_i = group.g.modpow(&key.x, &group.p);
+        let context = share_context(self.index);
+        let mut shares = Vec::with_capacity(cipher_pairs.len());
+        let mut proofs = Vec::with_capacity(cipher_pairs.len());
+        for (c, _) in &cipher_pairs {
+            let s = c.modpow(&key.x, &group.p);
+            let statement = DleqStatement {
+                p: &group.p,
+                order: &group.q,
+                g: &group.g,
+                h: &h_i,
+                c,
+                s: &s,
+            };
+            proofs.push(ChaumPedersenProof::prove(&statement, &key.x, &context, rng));
+            shares.push(s);
+        }
+        Some(DecryptionShare {
+            index: self.index,
+            shares,
+            proofs,
+        })
+    }
+}
+
+/// Runs the whole distributed key generation in process, with every party honest.
+/// Useful for tests and for simulating a validator set.
+pub fn simulate_dkg<R: RngCore>(
+    group: &Arc<GroupParams>,
+    params: ThresholdParams,
+    rng: &mut R,
+) -> ThresholdResult<(ThresholdPublicKey, Vec<KeyShare>)> {
+    let participants = (1..=params.parties)
+        .map(|i| DkgParticipant::new(i, params, group, rng))
+        .collect::<ThresholdResult<Vec<_>>>()?;
+    let commitments: Vec<(usize, Vec<BigInt>)> = participants
+        .iter()
+        .map(|dealer| (dealer.index(), dealer.commitments().to_vec()))
+        .collect();
+    let public_key = ThresholdPublicKey::from_commitments(group, params, &commitments)?;
+    let key_shares = participants
+        .iter()
+        .map(|recipient| {
+            let dealings = participants
+                .iter()
+                .map(|dealer| dealer.dealing_for(recipient.index()))
+                .collect::<ThresholdResult<Vec<_>>>()?;
+            recipient.finalize(&dealings)
+        })
+        .collect::<ThresholdResult<Vec<_>>>()?;
+    Ok((public_key, key_shares))
+}
+
+/// Checks that g generates the quadratic residue subgroup of order q.
+fn check_group(group: &GroupParams) -> ThresholdResult<()> {
+    if !is_subgroup_generator(&group.g, &group.p, &group.q) {
+        return Err(ThresholdError::InvalidParams);
+    }
+    Ok(())
+}
+
+/// Checks that every commitment is a quadratic residue in [1, p - 1], i.e. lies in the
+/// order q subgroup the shares are computed in.
+fn valid_commitments(group: &GroupParams, commitments: &[BigInt]) -> bool {
+    commitments
+        .iter()
+        .all(|c| in_group(c, &group.p) && utils::is_quadratic_residue(c, &group.p))
+}
+
+/// product of C[k]^(j^k) mod p, i.e. g^f(j) for the committed polynomial f.
+fn eval_commitments(group: &GroupParams, commitments: &[BigInt], j: usize) -> BigInt {
+    let j = BigInt::from(j);
+    let mut j_k = BigInt::from(1);
+    let mut result = BigInt::from(1);
+    for commitment in commitments {
+        result = (result * commitment.modpow(&j_k, &group.p)).mod_floor(&group.p);
+        j_k = (j_k * &j).mod_floor(&group.q);
+    }
+    result
+}
+
+/// Lagrange coefficient at zero of index i over the index set, mod q.
+fn lagrange_coefficient(i: usize, indices: &[usize], q: &BigInt) -> BigInt {
+    let i_int = BigInt::from(i);
+    let mut numerator = BigInt::from(1);
+    let mut denominator = BigInt::from(1);
+    for &j in indices.iter().filter(|&&j| j != i) {
+        let j_int = BigInt::from(j);
+        numerator = (numerator * &j_int).mod_floor(q);
+        denominator = (denominator * (&j_int - &i_int)).mod_floor(q);
+    }
+    (numerator * utils::mod_inverse(&denominator, q)).mod_floor(q)
+}
+
+fn share_context(index: usize) -> Vec<u8> {
+    let mut context = b"elgamal_wasm/threshold/decryption-share".to_vec();
+    context.extend_from_slice(&(index as u64).to_le_bytes());
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::Encryption;
+
+    const SEED: [u32; 1] = [2929];
+
+    #[test]
+    fn test_threshold_decrypt_with_any_t_shares() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let group = group_key.group;
+        let params = ThresholdParams {
+            threshold: 3,
+            parties: 5,
+        };
+        let (public_key, key_shares) = simulate_dkg(&group, params, &mut rng).unwrap();
+        let msg = String::from("threshold message");
+        let cipher = msg.encrypt(&public_key.key, &mut rng).unwrap();
+        let shares: Vec<DecryptionShare> = key_shares
+            .iter()
+            .map(|key_share| key_share.partial_decrypt(&cipher, &mut rng).unwrap())
+            .collect();
+        for subset in [[0, 1, 2], [0, 2, 4], [1, 3, 4]] {
+            let chosen: Vec<DecryptionShare> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(Ok(msg.clone()), public_key.combine(&cipher, &chosen));
+        }
+        assert_eq!(
+            Err(ThresholdError::NotEnoughShares),
+            public_key.combine(&cipher, &shares[..2])
+        );
+        // duplicated shares do not count twice
+        let duplicated = vec![shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert_eq!(
+            Err(ThresholdError::NotEnoughShares),
+            public_key.combine(&cipher, &duplicated)
+        );
+    }
+
+    #[test]
+    fn test_tampered_share_is_rejected() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let group = group_key.group;
+        let params = ThresholdParams {
+            threshold: 2,
+            parties: 3,
+        };
+        let (public_key, key_shares) = simulate_dkg(&group, params, &mut rng).unwrap();
+        let cipher = String::from("tamper")
+            .encrypt(&public_key.key, &mut rng)
+            .unwrap();
+        let mut bad = key_shares[0].partial_decrypt(&cipher, &mut rng).unwrap();
+        bad.shares[0] = (&bad.shares[0] * &group.g) % &group.p;
+        assert!(!public_key.verify_share(&cipher, &bad));
+        let good = key_shares[1].partial_decrypt(&cipher, &mut rng).unwrap();
+        assert!(public_key.verify_share(&cipher, &good));
+        assert_eq!(
+            Err(ThresholdError::NotEnoughShares),
+            public_key.combine(&cipher, &[bad, good])
+        );
+    }
+
+    #[test]
+    fn test_invalid_dealing_is_detected() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let group = group_key.group;
+        let params = ThresholdParams {
+            threshold: 2,
+            parties: 2,
+        };
+        let dealer = DkgParticipant::new(1, params, &group, &mut rng).unwrap();
+        let recipient = DkgParticipant::new(2, params, &group, &mut rng).unwrap();
+        let mut dealing = dealer.dealing_for(2).unwrap();
+        dealing.share += 1;
+        let own = recipient.dealing_for(2).unwrap();
+        assert_eq!(
+            Err(ThresholdError::InvalidDealing { dealer: 1 }),
+            recipient.finalize(&[dealing, own]).map(|share| share.index)
+        );
+        assert_eq!(
+            Err(ThresholdError::InvalidIndex(3)),
+            dealer.dealing_for(3).map(|dealing| dealing.dealer)
+        );
+
+        // -C[1] is not a quadratic residue but (-C[1])^(2^1) still matches the share of party 2
+        let mut negated = dealer.dealing_for(2).unwrap();
+        negated.commitments[1] = &group.p - &negated.commitments[1];
+        assert_eq!(
+            group.g.modpow(&negated.share, &group.p),
+            eval_commitments(&group, &negated.commitments, 2)
+        );
+        assert!(!negated.verify(&group, &params, 2));
+        assert_eq!(
+            Err(ThresholdError::InvalidDealing { dealer: 1 }),
+            ThresholdPublicKey::from_commitments(&group, params, &[(1, negated.commitments)])
+                .map(|key| key.verification_keys)
+        );
+    }
+
+    #[test]
+    fn test_repeated_dealer_and_legacy_group_are_rejected() {
+        let (group_key, mut rng) = crate::generate_qr_pub_key(&SEED, 64, 16);
+        let group = group_key.group;
+        let params = ThresholdParams {
+            threshold: 2,
+            parties: 3,
+        };
+        let dealer = DkgParticipant::new(1, params, &group, &mut rng).unwrap();
+        let recipient = DkgParticipant::new(2, params, &group, &mut rng).unwrap();
+        let dealing = dealer.dealing_for(2).unwrap();
+        let own = recipient.dealing_for(2).unwrap();
+        assert_eq!(
+            Err(ThresholdError::InvalidParams),
+            recipient
+                .finalize(&[dealing.clone(), own, dealing])
+                .map(|share| share.index)
+        );
+        let commitments = dealer.commitments().to_vec();
+        assert_eq!(
+            Err(ThresholdError::InvalidParams),
+            ThresholdPublicKey::from_commitments(
+                &group,
+                params,
+                &[(1, commitments.clone()), (1, commitments.clone())]
+            )
+            .map(|key| key.verification_keys)
+        );
+
+        // g of a legacy group is a primitive root, not of order q
+        let (legacy_key, mut rng) = crate::generate_pub_key(&SEED, 64, 16);
+        let legacy = legacy_key.group;
+        assert!(matches!(
+            DkgParticipant::new(1, params, &legacy, &mut rng),
+            Err(ThresholdError::InvalidParams)
+        ));
+        assert_eq!(
+            Err(ThresholdError::InvalidParams),
+            ThresholdPublicKey::from_commitments(&legacy, params, &[(1, commitments)])
+                .map(|key| key.verification_keys)
+        );
+    }
+}
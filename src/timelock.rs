@@ -0,0 +1,230 @@
+//! timelock mod
+//! time-lock encryption on the `yield_pubkey` key chain: a payload is sealed to the
+//! public key a number of steps ahead of the current chain head, and can be opened
+//! once the private key of that future key is known, e.g. brute forced with
+//! `utils::discrete_log`. the payload goes into an `Envelope` for the target key,
+//! the capsule records the chain head, the steps and the target next to it.
+use crate::envelope::{self, Envelope, EnvelopeError};
+use crate::generic::{PrivateKey, PublicKey};
+use crate::{KeyGenerator, RawKey, RawPublicKey};
+use codec::{Decode, Encode};
+use num_bigint::BigInt;
+use rand_core::RngCore;
+
+/// Errors of opening capsules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLockError {
+    /// The private key is not the key of the capsule's target.
+    WrongKey,
+    /// The envelope failed authentication, it was changed or is malformed.
+    InvalidCiphertext,
+}
+
+/// Type alias for time-lock results.
+pub type TimeLockResult<T> = std::result::Result<T, TimeLockError>;
+
+/// A position on the key chain: `steps` calls of `yield_pubkey(bit_length)` after `head`.
+#[derive(Debug, Clone)]
+pub struct TimeLock {
+    pub head: PublicKey<BigInt>,
+    pub steps: u32,
+    pub bit_length: u32,
+}
+
+/// A payload sealed to the key `steps` after `head` on the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Capsule {
+    pub head: RawPublicKey,
+    pub steps: u32,
+    pub bit_length: u32,
+    /// The chain key the payload is sealed to.
+    pub target: RawPublicKey,
+    pub envelope: Envelope,
+}
+
+impl TimeLock {
+    pub fn new(head: PublicKey<BigInt>, steps: u32, bit_length: u32) -> Self {
+        TimeLock {
+            head,
+            steps,
+            bit_length,
+        }
+    }
+
+    /// Starts from a raw chain head, e.g. the `head` of a capsule or a key read from SCALE.
+    /// The raw key carries its own group, no registry is needed.
+    pub fn from_raw(head: RawPublicKey, steps: u32, bit_length: u32) -> Self {
+        Self::new(PublicKey::from_raw(head), steps, bit_length)
+    }
+
+    /// Walks the chain from the head, returns the head itself for 0 steps.
+    pub fn target(&self) -> PublicKey<BigInt> {
+        (0..self.steps).fold(self.head.clone(), |key, _| {
+            key.yield_pubkey(self.bit_length)
+        })
+    }
+
+    /// Derives the target key and seals the payload to it.
+    ///
+    /// # Example
+    ///```rust
+    /// use elgamal_wasm as elgamal;
+    /// use elgamal::timelock::TimeLock;
+    /// let (head, mut rng) = elgamal::generate_pub_key(&[2929], 32, 16);
+    /// let capsule = TimeLock::new(head, 2, 32).seal(b"see you later", &mut rng);
+    /// // later: someone solves the discrete log of the target key
+    /// let target = capsule.target_key().unwrap();
+    /// let group = &target.group;
+    /// let order = &group.p - 1u32;
+    /// let x = elgamal::utils::discrete_log(&group.g, &target.h, &group.p, &order).unwrap();
+    /// let private_key = group.private_key(x);
+    /// assert_eq!(Ok(b"see you later".to_vec()), capsule.open(&private_key));
+    /// ```
+    ///
+    /// Panics for chain keys over the 256 bits of `RawPublicKey`.
+    pub fn seal<R: RngCore>(&self, payload: &[u8], rng: &mut R) -> Capsule {
+        let target = self.target();
+        let envelope = envelope::seal(&[&target], payload, rng).expect("one recipient");
+        Capsule {
+            head: self.head.clone().to_raw(),
+            steps: self.steps,
+            bit_length: self.bit_length,
+            target: target.to_raw(),
+            envelope,
+        }
+    }
+}
+
+impl Capsule {
+    /// The target as the public key to brute force, by walking the chain from the head.
+    /// Returns None if the walk does not end at the recorded target.
+    pub fn target_key(&self) -> Option<PublicKey<BigInt>> {
+        let head = PublicKey::from_raw(self.head.clone());
+        let target = TimeLock::new(head, self.steps, self.bit_length).target();
+        (target.clone().to_raw() == self.target).then_some(target)
+    }
+
+    /// Checks that the target is `steps` after `head` on the chain, by walking it again.
+    pub fn verify_chain(&self) -> bool {
+        self.target_key().is_some()
+    }
+
+    /// Opens the capsule with the private key of the target.
+    pub fn open(&self, key: &PrivateKey<BigInt>) -> TimeLockResult<Vec<u8>> {
+        let target = PublicKey::<BigInt>::from_raw(self.target.clone());
+        let group = &key.group;
+        if group.p != target.group.p
+            || group.g != target.group.g
+            || group.g.modpow(&key.x, &group.p) != target.h
+        {
+            return Err(TimeLockError::WrongKey);
+        }
+        self.envelope.open(key).map_err(|err| match err {
+            EnvelopeError::NoSlot | EnvelopeError::NoRecipients => TimeLockError::WrongKey,
+            EnvelopeError::InvalidCiphertext => TimeLockError::InvalidCiphertext,
+        })
+    }
+
+    /// SCALE encoding of the capsule.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode()
+    }
+
+    /// Decodes the SCALE encoding of a capsule, the input must be consumed entirely.
+    pub fn from_bytes(bytes: &[u8]) -> TimeLockResult<Self> {
+        let mut input = bytes;
+        let capsule = Capsule::decode(&mut input).map_err(|_| TimeLockError::InvalidCiphertext)?;
+        if !input.is_empty() {
+            return Err(TimeLockError::InvalidCiphertext);
+        }
+        Ok(capsule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    const SEED: [u32; 1] = [2929];
+
+    /// Recovers the private key of a small chain key.
+    fn crack(target: &PublicKey<BigInt>) -> PrivateKey<BigInt> {
+        let group = &target.group;
+        let order = &group.p - 1u32;
+        let x = utils::discrete_log(&group.g, &target.h, &group.p, &order).unwrap();
+        group.private_key(x)
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let (head, mut rng) = crate::generate_pub_key(&SEED, 32, 16);
+        let lock = TimeLock::new(head.clone(), 3, 32);
+        let capsule = lock.seal(b"time-locked payload", &mut rng);
+
+        let expected = head.yield_pubkey(32).yield_pubkey(32).yield_pubkey(32);
+        assert_eq!(expected.to_raw(), capsule.target);
+        assert!(capsule.verify_chain());
+        let target_key = crack(&capsule.target_key().unwrap());
+        assert_eq!(
+            Ok(b"time-locked payload".to_vec()),
+            capsule.open(&target_key)
+        );
+
+        // the key one step early does not open it
+        let early_key = crack(&head.yield_pubkey(32).yield_pubkey(32));
+        assert_eq!(Err(TimeLockError::WrongKey), capsule.open(&early_key));
+        assert_eq!(head.h, TimeLock::new(head.clone(), 0, 32).target().h);
+
+        let restored = Capsule::from_bytes(&capsule.to_bytes()).unwrap();
+        assert_eq!(capsule, restored);
+        assert!(restored.verify_chain());
+        let mut trailing = capsule.to_bytes();
+        trailing.push(0);
+        assert_eq!(
+            Err(TimeLockError::InvalidCiphertext),
+            Capsule::from_bytes(&trailing)
+        );
+    }
+
+    #[test]
+    fn test_seal_from_raw_head() {
+        let (head, mut rng) = crate::generate_pub_key(&SEED, 32, 16);
+        let raw_head = head.clone().to_raw();
+        let capsule = TimeLock::from_raw(raw_head.clone(), 2, 32).seal(b"raw head", &mut rng);
+        assert_eq!(raw_head, capsule.head);
+        assert_eq!(raw_head.yield_pubkey(32).yield_pubkey(32), capsule.target);
+
+        // a decoded capsule is verified and opened from its own bytes alone
+        let restored = Capsule::from_bytes(&capsule.to_bytes()).unwrap();
+        assert!(restored.verify_chain());
+        let target_key = crack(&restored.target_key().unwrap());
+        assert_eq!(Ok(b"raw head".to_vec()), restored.open(&target_key));
+        // and the chain goes on from the recorded target
+        let next = TimeLock::from_raw(restored.target, 1, 32).target();
+        assert_eq!(
+            head.yield_pubkey(32).yield_pubkey(32).yield_pubkey(32).h,
+            next.h
+        );
+    }
+
+    #[test]
+    fn test_tampered_capsule() {
+        let (head, mut rng) = crate::generate_pub_key(&SEED, 32, 16);
+        let capsule = TimeLock::new(head, 1, 32).seal(b"payload", &mut rng);
+        let target_key = crack(&capsule.target_key().unwrap());
+
+        let mut moved = capsule.clone();
+        moved.steps = 2;
+        assert!(!moved.verify_chain());
+        let mut other_head = capsule.clone();
+        other_head.head = capsule.target.clone();
+        assert_eq!(None, other_head.target_key().map(|key| key.h));
+        let mut changed = capsule;
+        changed.envelope.payload[0] ^= 1;
+        assert_eq!(
+            Err(TimeLockError::InvalidCiphertext),
+            changed.open(&target_key)
+        );
+    }
+}
@@ -9,9 +9,15 @@ use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::Integer;
 use num_traits::cast::ToPrimitive;
 use num_traits::identities::Zero;
+use std::collections::HashMap;
 
 /** These real versions are due to Kaisuki, 2021/01/07 added */
-/// random generator for bigint
+/// random generator for bigint, an integer in [start, stop] inclusive.
+///
+/// `gen_bigint_range` and `getrandbits` draw from the rng exactly like CPython's
+/// `random.randint` and `random.getrandbits`. With an MT19937 seeded like `random.seed`,
+/// keys match the Python reference in `tests/vectors/reference.py`, checked against its
+/// corpus by `tests/python_vectors.rs`. Changing the draws forks every key chain.
 pub fn gen_bigint_range<R: rand_core::RngCore>(
     rng: &mut R,
     start: &BigInt,
@@ -27,7 +33,7 @@ pub fn gen_bigint_range<R: rand_core::RngCore>(
 }
 
 /// Return an integer with k random bits with mt19937 random rng.
-fn getrandbits<R: rand_core::RngCore>(rng: &mut R, k: usize) -> BigInt {
+pub fn getrandbits<R: rand_core::RngCore>(rng: &mut R, k: usize) -> BigInt {
     if k == 0 {
         return BigInt::from_slice(Sign::NoSign, &[0]);
     }
@@ -148,6 +154,68 @@ pub fn find_h_bigint(p: &BigInt, r: &mut mt19937::MT19937) -> BigInt {
     gen_bigint_range(r, &range_num_low, &range_num_high)
 }
 
+/// Finds a generator of the order q subgroup of quadratic residues for the safe prime p = 2q + 1.
+///
+/// Every square other than 1 generates the subgroup, because its order divides q and q is prime.
+pub fn find_qr_generator_bigint(p: &BigInt, r: &mut mt19937::MT19937) -> BigInt {
+    let big_int_1 = BigInt::from(1);
+    let big_int_2 = BigInt::from(2);
+    let range_num_high: BigInt = p - &big_int_2;
+    loop {
+        let a = gen_bigint_range(r, &big_int_2, &range_num_high);
+        let g = a.modpow(&big_int_2, p);
+        if g != big_int_1 {
+            return g;
+        }
+    }
+}
+
+/// generate h in the quadratic residue subgroup for public_key
+pub fn find_qr_h_bigint(p: &BigInt, r: &mut mt19937::MT19937) -> BigInt {
+    find_h_bigint(p, r).modpow(&BigInt::from(2), p)
+}
+
+/// Checks if a is a quadratic residue mod the safe prime p, with Euler's criterion.
+pub fn is_quadratic_residue(a: &BigInt, p: &BigInt) -> bool {
+    let q: BigInt = (p - 1u32) / 2u32;
+    a.modpow(&q, p) == BigInt::from(1)
+}
+
+/// Computes a^-1 mod the prime p with Fermat's little theorem, a^(p-2) mod p.
+pub fn mod_inverse(a: &BigInt, p: &BigInt) -> BigInt {
+    let p_2 = p - BigInt::from(2);
+    a.mod_floor(p).modpow(&p_2, p)
+}
+
+/// Solves g^x = h mod p for x in [0, order) with baby-step giant-step.
+/// Returns None if there is no solution.
+/// # Logic Desc
+/// ```text
+/// m = ceil(sqrt(order))
+/// baby steps: g^j for j in [0, m)
+/// giant steps: h * g^(-m*i) for i in [0, m), matching g^j gives x = i*m + j
+/// ```
+/// Time and memory grow with sqrt(order), only small groups can be solved.
+pub fn discrete_log(g: &BigInt, h: &BigInt, p: &BigInt, order: &BigInt) -> Option<BigInt> {
+    let m = order.sqrt() + 1u32;
+    let steps = m.to_usize()?;
+    let mut baby_steps = HashMap::with_capacity(steps);
+    let mut e = BigInt::from(1);
+    for j in 0..steps {
+        baby_steps.entry(e.clone()).or_insert(j);
+        e = (e * g).mod_floor(p);
+    }
+    let giant_step = mod_inverse(&g.modpow(&m, p), p);
+    let mut gamma = h.mod_floor(p);
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&gamma) {
+            return Some((&m * i + *j).mod_floor(order));
+        }
+        gamma = (gamma * &giant_step).mod_floor(p);
+    }
+    None
+}
+
 /// Solovay-strassen primality test.
 ///     This function tests if num is prime.
 ///     http://www-math.ucdenver.edu/~wcherowi/courses/m5410/ctcprime.html
@@ -229,23 +297,36 @@ pub fn jacobi(a: &BigInt, n: &BigInt) -> BigInt {
 }
 
 /// Computes the jacobi symbol of a, n.If don't match any pattern or a cannot convert to i64
+/// Iterative, so the depth does not grow with the bit length of a and n.
 fn jacobi_match_else(a: &BigInt, n: &BigInt) -> BigInt {
     let big_int_0 = BigInt::from(0);
-    let big_int_2 = BigInt::from(2);
-    let big_int_r1 = BigInt::from(-1);
+    let big_int_1 = BigInt::from(1);
     let big_int_3 = BigInt::from(3);
     let big_int_4 = BigInt::from(4);
-    if a > n {
-        let tmp_a = a.mod_floor(n);
-        jacobi(&tmp_a, n)
-    } else if a.mod_floor(&big_int_2) == big_int_0 {
-        let tmp_a2 = a / &big_int_2;
-        jacobi(&big_int_2, n) * jacobi(&tmp_a2, n)
-    } else if (a.mod_floor(&big_int_4) == big_int_3.clone())
-        && (n.mod_floor(&big_int_4) == big_int_3)
-    {
-        big_int_r1 * jacobi(n, a)
+    let big_int_5 = BigInt::from(5);
+    let big_int_8 = BigInt::from(8);
+    let mut a = a.mod_floor(n);
+    let mut n = n.clone();
+    let mut result = big_int_1.clone();
+    while a != big_int_0 {
+        // (2/n) = -1 when n = 3 or 5 mod 8
+        while a.is_even() {
+            a >>= 1;
+            let n_mod_8 = n.mod_floor(&big_int_8);
+            if n_mod_8 == big_int_3 || n_mod_8 == big_int_5 {
+                result = -result;
+            }
+        }
+        // quadratic reciprocity
+        std::mem::swap(&mut a, &mut n);
+        if a.mod_floor(&big_int_4) == big_int_3 && n.mod_floor(&big_int_4) == big_int_3 {
+            result = -result;
+        }
+        a = a.mod_floor(&n);
+    }
+    if n == big_int_1 {
+        result
     } else {
-        jacobi(n, a)
+        big_int_0
     }
 }
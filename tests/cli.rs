@@ -0,0 +1,114 @@
+//! end to end tests of the elgamal binary.
+#![cfg(feature = "cli")]
+use std::process::Command;
+
+fn elgamal(args: &[&str]) -> Result<Vec<String>, String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_elgamal"))
+        .args(args)
+        .output()
+        .unwrap();
+    if output.status.success() {
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        Ok(stdout.lines().map(str::to_string).collect())
+    } else {
+        Err(String::from_utf8(output.stderr).unwrap())
+    }
+}
+
+#[test]
+fn test_keygen_encrypt_decrypt() {
+    for format in ["hex", "json"] {
+        let keys = elgamal(&[
+            "keygen", "--group", "modp1536", "--seed", "2929", "--format", format,
+        ])
+        .unwrap();
+        let cipher = elgamal(&[
+            "encrypt",
+            "--key",
+            &keys[0],
+            "--message",
+            "cli message",
+            "--format",
+            format,
+        ])
+        .unwrap();
+        let plaintext = elgamal(&[
+            "decrypt",
+            "--key",
+            &keys[1],
+            "--ciphertext",
+            &cipher[0],
+            "--format",
+            format,
+        ])
+        .unwrap();
+        assert_eq!(vec!["cli message".to_string()], plaintext);
+    }
+    // a bit length too large for p is an error, not a panic
+    let err = elgamal(&["encrypt", "--key", "0x17, 0x5, 0x3, 32", "--message", "m"]).unwrap_err();
+    assert!(err.contains("too large"));
+}
+
+#[test]
+fn test_yield_matches_library() {
+    use elgamal_wasm::{generate_pub_key, KeyFormat, KeyGenerator};
+    let chain = elgamal(&["yield", "--seed", "2929", "--bits", "32", "--steps", "2"]).unwrap();
+    assert_eq!(3, chain.len());
+    let (mut pubkey, _) = generate_pub_key(&[2929], 32, 16);
+    for key in chain {
+        assert_eq!(pubkey.to_hex_str(), key);
+        pubkey = pubkey.yield_pubkey(32);
+    }
+    // resume the chain from a SCALE encoded key
+    let scale = elgamal(&[
+        "keygen", "--seed", "2929", "--bits", "32", "--format", "scale",
+    ])
+    .unwrap();
+    let resumed = elgamal(&["yield", "--key", &scale[0], "--format", "scale"]).unwrap();
+    let expected = elgamal(&[
+        "yield", "--seed", "2929", "--bits", "32", "--format", "scale",
+    ])
+    .unwrap();
+    assert_eq!(expected[1], resumed[0]);
+    // the chain keeps the key's bit length
+    let err = elgamal(&[
+        "yield", "--key", &scale[0], "--bits", "256", "--format", "scale",
+    ])
+    .unwrap_err();
+    assert!(err.contains("conflicts"));
+
+    // subcommands with private keys or ciphertexts refuse SCALE before printing anything
+    for args in [
+        vec!["keygen", "--group", "ffdhe2048", "--seed", "2929"],
+        vec!["encrypt", "--key", &scale[0], "--message", "scale"],
+        vec!["decrypt", "--key", &scale[0], "--ciphertext", "00"],
+        vec!["crack", "--key", &scale[0]],
+    ] {
+        let err = elgamal(&[args.as_slice(), &["--format", "scale"]].concat()).unwrap_err();
+        assert!(err.contains("only holds public keys"), "{}", err);
+    }
+}
+
+#[test]
+fn test_validate_and_crack() {
+    let key = elgamal(&["keygen", "--seed", "2929", "--bits", "32"]).unwrap();
+    assert_eq!(
+        vec!["valid".to_string()],
+        elgamal(&["validate", "--key", &key[0]]).unwrap()
+    );
+    let not_safe = "0x747c85d9, 0x747c85d6, 0xb2040843, 32";
+    assert!(elgamal(&["validate", "--key", not_safe]).is_err());
+
+    let private_key = elgamal(&["crack", "--key", &key[0]]).unwrap();
+    let cipher = elgamal(&["encrypt", "--key", &key[0], "--message", "cracked"]).unwrap();
+    let plaintext = elgamal(&[
+        "decrypt",
+        "--key",
+        &private_key[0],
+        "--ciphertext",
+        &cipher[0],
+    ])
+    .unwrap();
+    assert_eq!(vec!["cracked".to_string()], plaintext);
+    assert!(elgamal(&["crack", "--key", &key[0], "--max-bits", "16"]).is_err());
+}
@@ -0,0 +1,121 @@
+//! checks the corpus of tests/vectors/reference.py, the CPython reference implementation.
+//! regenerate it with `python3 tests/vectors/reference.py > tests/vectors/python_reference.json`.
+use elgamal::generic::Encryption;
+use elgamal::signature::{self, SignatureScheme};
+use elgamal::utils;
+use elgamal_wasm as elgamal;
+use num_bigint::{BigInt, BigUint};
+use serde_json::Value;
+
+fn corpus() -> Value {
+    serde_json::from_str(include_str!("vectors/python_reference.json")).unwrap()
+}
+
+fn int(value: &Value) -> BigInt {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+/// MT19937 seeded like `random.Random(seed)`.
+fn rng(seed: &Value) -> mt19937::MT19937 {
+    mt19937::MT19937::new_with_slice_seed(&seed_words(seed))
+}
+
+fn seed_words(seed: &Value) -> Vec<u32> {
+    let seed: BigUint = seed.as_str().unwrap().parse().unwrap();
+    seed.to_u32_digits()
+}
+
+#[test]
+fn test_getrandbits_matches_python() {
+    for vector in corpus()["getrandbits"].as_array().unwrap() {
+        let mut rng = rng(&vector["seed"]);
+        let bits = vector["k"].as_array().unwrap();
+        for (k, expected) in bits.iter().zip(vector["values"].as_array().unwrap()) {
+            let k = k.as_u64().unwrap() as usize;
+            assert_eq!(int(expected), utils::getrandbits(&mut rng, k));
+        }
+    }
+}
+
+#[test]
+fn test_randint_matches_python() {
+    for vector in corpus()["randint"].as_array().unwrap() {
+        let mut rng = rng(&vector["seed"]);
+        let ranges = vector["ranges"].as_array().unwrap();
+        for (range, expected) in ranges.iter().zip(vector["values"].as_array().unwrap()) {
+            let (low, high) = (int(&range[0]), int(&range[1]));
+            assert_eq!(
+                int(expected),
+                utils::gen_bigint_range(&mut rng, &low, &high)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_pub_keys_match_python() {
+    for vector in corpus()["pub_keys"].as_array().unwrap() {
+        let seed = seed_words(&vector["seed"]);
+        let bit_length = vector["bit_length"].as_u64().unwrap() as u32;
+        let confidence = vector["confidence"].as_u64().unwrap() as u32;
+        let (pubkey, _) = match vector["scheme"].as_str().unwrap() {
+            "legacy" => elgamal::generate_pub_key(&seed, bit_length, confidence),
+            "qr" => elgamal::generate_qr_pub_key(&seed, bit_length, confidence),
+            scheme => panic!("unknown scheme {}", scheme),
+        };
+        assert_eq!(
+            (int(&vector["p"]), int(&vector["g"]), int(&vector["h"])),
+            (pubkey.group.p.clone(), pubkey.group.g.clone(), pubkey.h),
+            "{}",
+            vector
+        );
+    }
+}
+
+#[test]
+fn test_ciphertexts_match_python() {
+    for vector in corpus()["ciphertexts"].as_array().unwrap() {
+        let seed = seed_words(&vector["seed"]);
+        let bit_length = vector["bit_length"].as_u64().unwrap() as u32;
+        let confidence = vector["confidence"].as_u64().unwrap() as u32;
+        let (pubkey, mut rng) = elgamal::generate_pub_key(&seed, bit_length, confidence);
+        let message = vector["message"].as_str().unwrap().to_string();
+        assert_eq!(
+            vector["ciphertext"].as_str(),
+            message.encrypt(&pubkey, &mut rng).as_deref(),
+            "{}",
+            vector
+        );
+    }
+}
+
+#[test]
+fn test_signatures_match_python() {
+    for vector in corpus()["signatures"].as_array().unwrap() {
+        let seed = seed_words(&vector["seed"]);
+        let bit_length = vector["bit_length"].as_u64().unwrap() as u32;
+        let confidence = vector["confidence"].as_u64().unwrap() as u32;
+        let (scheme, (group_key, mut rng)) = match vector["scheme"].as_str().unwrap() {
+            "elgamal" => (
+                SignatureScheme::ElGamal,
+                elgamal::generate_pub_key(&seed, bit_length, confidence),
+            ),
+            "dsa" => (
+                SignatureScheme::Dsa,
+                elgamal::generate_qr_pub_key(&seed, bit_length, confidence),
+            ),
+            scheme => panic!("unknown scheme {}", scheme),
+        };
+        let (pubkey, private_key) = elgamal::generate_key_pair(&group_key.group, &mut rng);
+        assert_eq!(int(&vector["x"]), private_key.x);
+        let message = vector["message"].as_str().unwrap().as_bytes();
+        let signature = signature::sign(&private_key, scheme, message).unwrap();
+        assert_eq!(
+            (int(&vector["r"]), int(&vector["s"])),
+            (signature.r.clone(), signature.s.clone()),
+            "{}",
+            vector
+        );
+        assert!(signature::verify(&pubkey, scheme, message, &signature));
+    }
+}
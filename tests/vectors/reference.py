@@ -0,0 +1,262 @@
+#!/usr/bin/env python3
+"""Reference implementation of elgamal_wasm key generation, encryption and signatures.
+
+Uses CPython's random module, which runs MT19937 seeded with init_by_array over the
+32-bit digits of the seed. `utils::getrandbits` and `utils::gen_bigint_range` mirror
+`random.getrandbits` and `random.randint`, so the same seed gives the same keys in
+both implementations. Signatures use RFC 6979 nonces with HMAC-SHA-256.
+
+Regenerate the corpus with:
+
+    python3 tests/vectors/reference.py > tests/vectors/python_reference.json
+"""
+import hashlib
+import hmac
+import json
+import math
+import random
+import sys
+
+BOM = b"\xff\xfe"
+PAD_MARKER = 0x80
+CONFIDENCE = 16
+
+SEEDS = [
+    1,
+    2929,
+    123456789,
+    2**32,
+    833050814021254693158343911234888353695402778102174580258852673738983005,
+]
+
+
+def jacobi(a, n):
+    a %= n
+    result = 1
+    while a != 0:
+        while a % 2 == 0:
+            a //= 2
+            if n % 8 in (3, 5):
+                result = -result
+        a, n = n, a
+        if a % 4 == 3 and n % 4 == 3:
+            result = -result
+        a %= n
+    return result if n == 1 else 0
+
+
+def solovay_strassen(num, confidence, rng):
+    for _ in range(confidence):
+        a = rng.randint(1, num - 1)
+        if math.gcd(a, num) > 1:
+            return False
+        if jacobi(a, num) % num != pow(a, (num - 1) // 2, num):
+            return False
+    return True
+
+
+def gen_prime(bit_length, rng):
+    return rng.randint(2 ** (bit_length - 2), 2 ** (bit_length - 1))
+
+
+def random_prime(bit_length, confidence, rng):
+    while True:
+        p = gen_prime(bit_length, rng)
+        while p % 2 == 0:
+            p = gen_prime(bit_length, rng)
+        while not solovay_strassen(p, confidence, rng):
+            p = gen_prime(bit_length, rng)
+            while p % 2 == 0:
+                p = gen_prime(bit_length, rng)
+        p = p * 2 + 1
+        if solovay_strassen(p, confidence, rng):
+            return p
+
+
+def find_primitive_root(p, rng):
+    if p == 2:
+        return 1
+    p2 = (p - 1) // 2
+    p3 = (p - 1) // p2
+    while True:
+        g = rng.randint(2, p - 1)
+        if pow(g, p2, p) != 1 and pow(g, p3, p) != 1:
+            return g
+
+
+def find_h(p, rng):
+    return rng.randint(1, p - 1)
+
+
+def find_qr_generator(p, rng):
+    while True:
+        g = pow(rng.randint(2, p - 2), 2, p)
+        if g != 1:
+            return g
+
+
+def generate_pub_key(seed, bit_length, confidence, qr=False):
+    """`generate_pub_key` and `generate_qr_pub_key`, returning the key and the rng."""
+    rng = random.Random(seed)
+    p = random_prime(bit_length, confidence, rng)
+    rng = random.Random(seed)
+    g = find_qr_generator(p, rng) if qr else find_primitive_root(p, rng)
+    rng = random.Random(seed)
+    h = find_h(p, rng)
+    if qr:
+        h = pow(h, 2, p)
+    return (p, g, h), rng
+
+
+def encode_message(message, p, bit_length):
+    k = (bit_length - 2) // 8
+    data = BOM + message.encode("utf-16-le") + bytes([PAD_MARKER])
+    data += bytes(-len(data) % k)
+    q = (p - 1) // 2
+    codes = []
+    for i in range(0, len(data), k):
+        m = int.from_bytes(data[i : i + k], "little") + 1
+        codes.append(m if pow(m, q, p) == 1 else p - m)
+    return codes
+
+
+def encrypt(message, key, bit_length, rng):
+    p, g, h = key
+    cipher = ""
+    for code in encode_message(message, p, bit_length):
+        y = rng.randint(0, p)
+        cipher += "%d %d " % (pow(g, y, p), code * pow(h, y, p) % p)
+    return cipher
+
+
+def bits2int(data, qlen):
+    value = int.from_bytes(data, "big")
+    blen = len(data) * 8
+    return value >> (blen - qlen) if blen > qlen else value
+
+
+def rfc6979_nonces(x, order, h1):
+    """Candidate nonces of RFC 6979 section 3.2 with HMAC-SHA-256."""
+    qlen = order.bit_length()
+    rolen = (qlen + 7) // 8
+    seed = x.to_bytes(rolen, "big") + (bits2int(h1, qlen) % order).to_bytes(rolen, "big")
+    mac = lambda key, data: hmac.new(key, data, hashlib.sha256).digest()
+    v = b"\x01" * 32
+    k = b"\x00" * 32
+    k = mac(k, v + b"\x00" + seed)
+    v = mac(k, v)
+    k = mac(k, v + b"\x01" + seed)
+    v = mac(k, v)
+    while True:
+        t = b""
+        while len(t) * 8 < qlen:
+            v = mac(k, v)
+            t += v
+        candidate = bits2int(t, qlen)
+        if 1 <= candidate < order:
+            yield candidate
+        k = mac(k, v + b"\x00")
+        v = mac(k, v)
+
+
+def sign(scheme, p, g, x, message):
+    order = p - 1 if scheme == "elgamal" else (p - 1) // 2
+    h1 = hashlib.sha256(message).digest()
+    e = bits2int(h1, order.bit_length()) % order
+    for k in rfc6979_nonces(x % order, order, h1):
+        if scheme == "elgamal":
+            if math.gcd(k, order) != 1:
+                continue
+            r = pow(g, k, p)
+            s = (e - x * r) * pow(k, -1, order) % order
+        else:
+            r = pow(g, k, p) % order
+            s = pow(k, -1, order) * (e + x * r) % order
+        if r != 0 and s != 0:
+            return r, s
+
+
+def main():
+    corpus = {
+        "getrandbits": [],
+        "randint": [],
+        "pub_keys": [],
+        "ciphertexts": [],
+        "signatures": [],
+    }
+    for seed in SEEDS:
+        rng = random.Random(seed)
+        bits = [1, 7, 31, 32, 33, 64, 65, 100, 256]
+        corpus["getrandbits"].append(
+            {
+                "seed": str(seed),
+                "k": bits,
+                "values": [str(rng.getrandbits(k)) for k in bits],
+            }
+        )
+        rng = random.Random(seed)
+        ranges = [(0, 1), (1, 6), (2**31, 2**33), (0, 10**30), (-5, 5)]
+        corpus["randint"].append(
+            {
+                "seed": str(seed),
+                "ranges": [[str(low), str(high)] for low, high in ranges],
+                "values": [str(rng.randint(low, high)) for low, high in ranges],
+            }
+        )
+        for scheme, bit_length in [("legacy", 16), ("legacy", 32), ("legacy", 64), ("qr", 32)]:
+            (p, g, h), _ = generate_pub_key(seed, bit_length, CONFIDENCE, scheme == "qr")
+            corpus["pub_keys"].append(
+                {
+                    "seed": str(seed),
+                    "scheme": scheme,
+                    "bit_length": bit_length,
+                    "confidence": CONFIDENCE,
+                    "p": str(p),
+                    "g": str(g),
+                    "h": str(h),
+                }
+            )
+    for seed, bit_length, message in [
+        (2929, 32, "message for encrypt"),
+        (123456789, 64, "Python reference é中\U0001f600"),
+        (2**32, 128, ""),
+    ]:
+        key, rng = generate_pub_key(seed, bit_length, CONFIDENCE)
+        corpus["ciphertexts"].append(
+            {
+                "seed": str(seed),
+                "bit_length": bit_length,
+                "confidence": CONFIDENCE,
+                "message": message,
+                "ciphertext": encrypt(message, key, bit_length, rng),
+            }
+        )
+    for seed, bit_length, scheme in [
+        (2929, 64, "elgamal"),
+        (2929, 64, "dsa"),
+        (123456789, 128, "elgamal"),
+        (123456789, 128, "dsa"),
+    ]:
+        # generate_key_pair after the public key, x = randint(1, q - 1)
+        (p, g, _), rng = generate_pub_key(seed, bit_length, CONFIDENCE, scheme == "dsa")
+        x = rng.randint(1, (p - 1) // 2 - 1)
+        for message in ["sample", "test", ""]:
+            r, s = sign(scheme, p, g, x, message.encode())
+            corpus["signatures"].append(
+                {
+                    "seed": str(seed),
+                    "bit_length": bit_length,
+                    "confidence": CONFIDENCE,
+                    "scheme": scheme,
+                    "x": str(x),
+                    "message": message,
+                    "r": str(r),
+                    "s": str(s),
+                }
+            )
+    json.dump(corpus, sys.stdout, indent=1, ensure_ascii=False)
+    sys.stdout.write("\n")
+
+
+if __name__ == "__main__":
+    main()